@@ -0,0 +1,3 @@
+Clears a cell 65025 times in a tight loop then prints OK
+-[>-[>-[-]<-]<-]
+++++++++[>++++++++++<-]>-.----.
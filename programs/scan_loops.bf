@@ -0,0 +1,4 @@
+Marks 200 cells then sweeps over them with scan loops 65025 times then prints OK
+>>>>++++++++++[<++++++++++++++++++++>-]<[[->+<]+>-]<[<]
+<<-[>-[>>[>]<[<]<-]<-]
+++++++++[>++++++++++<-]>-.----.
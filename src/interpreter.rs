@@ -1,197 +1,1964 @@
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::fmt;
+use core::mem;
+use core::ops::Range;
+use core::time::Duration;
+#[cfg(feature = "std")]
 use std::fs::File;
-use std::io::{self, Read, Write};
-use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::io::BufReader;
+#[cfg(feature = "std")]
+use std::time::Instant;
 
-struct Lexer {
-    position_in_code: usize,
-    content: Vec<char>,
+use io::{BufRead, Cursor, Read, Write};
+
+pub mod io;
+pub mod ir;
+mod builder;
+mod disassemble;
+mod serialize;
+mod transpile;
+
+pub use builder::InterpreterBuilder;
+pub use ir::{IRInstruction, IRInstructionKind, SourceSpan};
+
+// Reads the source one byte at a time, so it never has to be held in memory as a whole.
+// Instructions are all ASCII, any other byte (including those of multi-byte characters) is a comment.
+struct Lexer<R: BufRead> {
+    source: R,
+    line: usize,
+    col: usize,
+    span: SourceSpan,
+    debug_breakpoints: bool,
 }
 
-impl Lexer {
-    pub fn new() -> Lexer {
+impl<R: BufRead> Lexer<R> {
+    pub fn new(source: R, debug_breakpoints: bool) -> Lexer<R> {
         Lexer {
-            position_in_code: 0,
-            content: Vec::new(),
+            source,
+            line: 1,
+            col: 1,
+            span: SourceSpan::default(),
+            debug_breakpoints,
         }
     }
 
-    pub fn fill(&mut self, code: &str) {
-        for c in code.chars() {
-            self.content.push(c);
+    fn is_valid_brainfuck_instruction(&self, inst: char) -> bool {
+        let valid = "><+-.,[]";
+        valid.contains(inst) || (self.debug_breakpoints && inst == '#')
+    }
+
+    fn advance(&mut self) -> io::Result<Option<u8>> {
+        let byte = match self.source.fill_buf()?.first() {
+            Some(&byte) => byte,
+            None => return Ok(None),
+        };
+
+        self.source.consume(1);
+
+        if byte == b'\n' {
+            self.line += 1;
+            self.col = 1;
+        } else if byte & 0xC0 != 0x80 {
+            // UTF-8 continuation bytes are part of the character before them.
+            self.col += 1;
         }
+
+        Ok(Some(byte))
     }
 
-    fn is_valid_brainfuck_instruction(&self, inst: char) -> bool {
-        let valid = "><+-.,[]";
-        if valid.contains(inst) {
-            return true;
-        } else {
-            return false;
+    /// The next instruction, or `None` once the source is exhausted.
+    pub fn next(&mut self) -> io::Result<Option<char>> {
+        loop {
+            let span = SourceSpan { line: self.line, col: self.col };
+
+            match self.advance()? {
+                None => return Ok(None),
+                Some(byte) if self.is_valid_brainfuck_instruction(byte as char) => {
+                    self.span = span;
+                    return Ok(Some(byte as char));
+                },
+                Some(_) => (),
+            }
+        }
+    }
+
+    /// Where the instruction last returned by `next` sits in the source.
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BracketError {
+    UnmatchedOpen { instruction_index: usize, span: SourceSpan },
+    UnmatchedClose { instruction_index: usize, span: SourceSpan },
+}
+
+impl fmt::Display for BracketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BracketError::UnmatchedOpen { span, .. } => write!(f, "Unmatched '[' at {span}"),
+            BracketError::UnmatchedClose { span, .. } => write!(f, "Unmatched ']' at {span}"),
+        }
+    }
+}
+
+impl core::error::Error for BracketError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapeError {
+    PointerUnderflow { instruction_pointer: usize, span: SourceSpan },
+    PointerOverflow { instruction_pointer: usize, span: SourceSpan },
+}
+
+impl fmt::Display for TapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TapeError::PointerUnderflow { instruction_pointer, span } => write!(f, "Memory pointer moved below cell 0 at {span} (instruction {instruction_pointer})"),
+            TapeError::PointerOverflow { instruction_pointer, span } => write!(f, "Memory pointer moved past the end of the tape at {span} (instruction {instruction_pointer})"),
         }
     }
+}
+
+impl core::error::Error for TapeError {}
+
+/// Every way loading or running a program can fail.
+#[derive(Debug)]
+pub enum SacError {
+    Io(io::Error),
+    UnbalancedBrackets(BracketError),
+    PointerOutOfBounds(TapeError),
+    StepLimit { max_steps: u64 },
+    Timeout { timeout: Duration },
+    LoopTooDeep { max_depth: usize, span: SourceSpan },
+}
 
-    pub fn next(&mut self) -> char {
-        while self.position_in_code < self.content.len() && !self.is_valid_brainfuck_instruction(self.content[self.position_in_code]) {
-            self.position_in_code += 1;
+impl fmt::Display for SacError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SacError::Io(e) => write!(f, "I/O failure : {e}"),
+            SacError::UnbalancedBrackets(e) => write!(f, "Invalid program : {e}"),
+            SacError::PointerOutOfBounds(e) => write!(f, "{e}"),
+            SacError::StepLimit { max_steps } => write!(f, "Step limit of {max_steps} instructions exceeded"),
+            SacError::Timeout { timeout } => write!(f, "Timeout of {timeout:?} exceeded"),
+            SacError::LoopTooDeep { max_depth, span } => write!(f, "Invalid program : loop at {span} nested deeper than {max_depth} levels"),
         }
+    }
+}
 
-        if self.position_in_code >= self.content.len() {
-            return '@'; // EOF character, randomly chosen.
+impl core::error::Error for SacError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            SacError::Io(e) => Some(e),
+            SacError::UnbalancedBrackets(e) => Some(e),
+            SacError::PointerOutOfBounds(e) => Some(e),
+            SacError::StepLimit { .. } | SacError::Timeout { .. } | SacError::LoopTooDeep { .. } => None,
         }
+    }
+}
+
+impl From<io::Error> for SacError {
+    fn from(e: io::Error) -> Self {
+        SacError::Io(e)
+    }
+}
+
+impl From<BracketError> for SacError {
+    fn from(e: BracketError) -> Self {
+        SacError::UnbalancedBrackets(e)
+    }
+}
+
+impl From<TapeError> for SacError {
+    fn from(e: TapeError) -> Self {
+        SacError::PointerOutOfBounds(e)
+    }
+}
+
+/// What `,` does to the current cell once the input is exhausted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EofBehavior {
+    Unchanged,
+    #[default]
+    Zero,
+    NegativeOne,
+}
+
+/// A tape cell. Arithmetic wraps at the cell width, `.` writes the low byte and `,` stores a byte.
+pub trait Cell: Copy + PartialEq + fmt::Debug + fmt::Display {
+    const ZERO: Self;
+    const MAX: Self;
+    // Unsigned C and Rust types of the same width, for transpiled programs.
+    const C_TYPE: &'static str;
+    const RUST_TYPE: &'static str;
+
+    fn wrapping_add_operand(self, operand: usize) -> Self;
+    fn wrapping_sub_operand(self, operand: usize) -> Self;
+    fn from_byte(byte: u8) -> Self;
+    fn low_byte(self) -> u8;
+    fn to_operand(self) -> usize;
+}
+
+macro_rules! impl_cell {
+    ($($t:ty => $c_type:literal),*) => {
+        $(
+            impl Cell for $t {
+                const ZERO: Self = 0;
+                const MAX: Self = <$t>::MAX;
+                const C_TYPE: &'static str = $c_type;
+                const RUST_TYPE: &'static str = stringify!($t);
+
+                // Truncating the operand is intended, runs wrap modulo the cell width too.
+                fn wrapping_add_operand(self, operand: usize) -> Self {
+                    self.wrapping_add(operand as $t)
+                }
+
+                fn wrapping_sub_operand(self, operand: usize) -> Self {
+                    self.wrapping_sub(operand as $t)
+                }
+
+                fn from_byte(byte: u8) -> Self {
+                    byte as $t
+                }
+
+                fn low_byte(self) -> u8 {
+                    self as u8
+                }
+
+                fn to_operand(self) -> usize {
+                    self as usize
+                }
+            }
+        )*
+    };
+}
+
+impl_cell!(u8 => "unsigned char", u16 => "unsigned short", u32 => "unsigned int");
+
+/// How `.` renders the current cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    /// The low byte of the cell, as is.
+    #[default]
+    Char,
+    /// The cell value in decimal followed by a space, handy for numeric programs.
+    Decimal,
+}
+
+/// What a single `step` executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepResult {
+    pub kind: IRInstructionKind,
+    pub produced_output: bool,
+}
+
+/// Execution state captured by `snapshot`. The program itself is not part of it, so it must only be
+/// restored into an interpreter running the same program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct State<C: Cell = u8> {
+    pub tape: Vec<C>,
+    pub memory_pointer: usize,
+    pub instruction_pointer: usize,
+    pub steps: u64,
+}
+
+/// Why `run_until_breakpoint` returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStop {
+    /// The instruction pointer reached the breakpoint set on this instruction, which has not run yet.
+    Breakpoint(usize),
+    /// The program finished.
+    Halted,
+}
+
+/// A loop `analyze_loops` found to never change the cell it tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopWarning {
+    /// IR index of the `[`.
+    pub open: usize,
+    /// IR index of the matching `]`.
+    pub close: usize,
+    pub span: SourceSpan,
+}
+
+impl fmt::Display for LoopWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Loop at {} never changes its own cell, it spins forever once entered", self.span)
+    }
+}
+
+/// Shape of the loaded program as returned by `validate`, counted on the optimized IR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProgramStats {
+    pub instruction_count: usize,
+    pub loop_count: usize,
+    /// 0 for a program without loops.
+    pub max_nesting_depth: usize,
+}
 
-        let r = self.content[self.position_in_code];
-        self.position_in_code += 1;
-        return r;
+impl ProgramStats {
+    /// True when there is nothing to run: the source had no instructions (e.g. an empty or comment-only
+    /// file) or only ones optimized away, like `+-` or `+[-]` at the start of a program.
+    pub fn is_empty(&self) -> bool {
+        self.instruction_count == 0
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
-enum IRInstructionKind {
-    IncrementPointer,
-    DecrementPointer,
-    IncrementByte,
-    DecrementByte,
-    PrintByteAsChar,
-    ReadInputToByte,
-    JumpIfZero,
-    JumpIfNotZero,
+/// Execution counts gathered while profiling, grouped by instruction kind, see `Interpreter::profile`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Profile {
+    /// Executions of each kind that ran at least once, most executed first.
+    pub counts: Vec<(IRInstructionKind, u64)>,
+    pub total_cycles: u64,
+    /// Loop bodies run, i.e. executions of `]`.
+    pub loop_iterations: u64,
 }
 
-#[derive(Clone, Copy)]
-struct IRInstruction {
-    kind: IRInstructionKind,
-    operand: Option<u8>,
+impl Profile {
+    pub fn count(&self, kind: IRInstructionKind) -> u64 {
+        self.counts.iter().find(|&&(k, _)| k == kind).map_or(0, |&(_, count)| count)
+    }
 }
 
 const RAM_SIZE: usize = 100_000;
 
-pub struct Interpreter {
+// Cells shown on each side of the memory pointer by the default breakpoint dump.
+const DEBUG_DUMP_RADIUS: usize = 4;
+
+// Default of `set_max_loop_depth`, far deeper than hand-written programs go.
+const DEFAULT_MAX_LOOP_DEPTH: usize = 256;
+
+// Most IR instructions `optimize_const_prefix` runs at load time before giving up on the rest.
+const CONST_PREFIX_STEP_LIMIT: usize = 16_384;
+
+// Number of steps between two clock reads when a timeout is set.
+#[cfg(feature = "std")]
+const TIMEOUT_POLL_INTERVAL: u64 = 4096;
+
+/// Selects which peephole passes `parse` runs on top of the run-length folded IR.
+/// Every combination runs programs that stay on the tape identically, only the IR and its speed differ.
+/// Merged moves can skip a step off the tape, so `<>` on cell 0 only fails with a `TapeError` unoptimized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Optimizations {
+    /// Merges adjacent `><` and `+-` runs into their net effect.
+    pub cancelling_runs: bool,
+    /// Turns `[-]` and `[+]` into `SetZero`.
+    pub clear_loops: bool,
+    /// Folds pointer moves into the offsets of the byte instructions around them.
+    pub offsets: bool,
+    /// Turns copy/multiply loops into `MultiplyAdd`, only matches anything when `offsets` is on.
+    pub multiply_loops: bool,
+    /// Turns `[>]` and `[<]` into `ScanRight` and `ScanLeft`.
+    pub scan_loops: bool,
+    /// Drops loops that can never run because their cell is known to be zero.
+    pub dead_loops: bool,
+    /// Runs the input-free start of the program at load time and replaces it with `SetCell`s.
+    pub const_prefix: bool,
+}
+
+impl Optimizations {
+    pub const ALL: Optimizations = Optimizations { cancelling_runs: true, clear_loops: true, offsets: true, multiply_loops: true, scan_loops: true, dead_loops: true, const_prefix: true };
+    pub const NONE: Optimizations = Optimizations { cancelling_runs: false, clear_loops: false, offsets: false, multiply_loops: false, scan_loops: false, dead_loops: false, const_prefix: false };
+}
+
+impl Default for Optimizations {
+    fn default() -> Self {
+        Optimizations::ALL
+    }
+}
+
+/// What happens when the memory pointer leaves the tape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PointerMode {
+    /// Stop with a `TapeError`.
+    #[default]
+    Error,
+    /// Come back from the other end of the tape. A `TapeMode::Growable` tape still grows
+    /// rightwards, so only moving below cell 0 wraps, and programs loaded in this combination
+    /// skip the same passes as with `Clamp`.
+    Wrap,
+    /// Stay on the first or last cell. Clamped moves don't add up (`><` on the last cell ends one
+    /// cell left of it), so programs loaded in this mode skip the passes that merge or fold moves.
+    Clamp,
+}
+
+/// How the tape reacts when the memory pointer moves past its last cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapeMode {
+    /// A tape of exactly this many cells, moving past it is a `PointerOverflow`.
+    Fixed(usize),
+    /// A tape extended with zeroed cells as needed, doubling its length each time.
+    Growable,
+}
+
+impl Default for TapeMode {
+    fn default() -> Self {
+        TapeMode::Fixed(RAM_SIZE)
+    }
+}
+
+/// A Brainfuck interpreter, used in two phases:
+///
+/// 1. Compile: `load_program_from_file` / `load_program_from_str` / `load_program_from_reader` stream
+///    the source through the lexer into IR and `parse` it into optimized IR with resolved jumps.
+///    Tooling can stop here (`disassemble`, `transpile_to_c`, ...).
+/// 2. Run: `interpret` / `step` only execute the IR and never re-parse, so after a `reset` the same
+///    compiled program can run again.
+pub struct Interpreter<C: Cell = u8> {
     memory_pointer: usize,
     instruction_pointer: usize,
-    ram: [u8; RAM_SIZE],
+    ram: Vec<C>,
+    tape_mode: TapeMode,
+    pointer_mode: PointerMode,
+    max_ram_size: Option<usize>,
     program: Vec<IRInstruction>,
-    jump_map: HashMap<usize, usize>,
-    lexer: Lexer,
+    debug_breakpoints: bool,
+    eof_behavior: EofBehavior,
+    output_mode: OutputMode,
+    interactive: bool,
+    steps: u64,
+    // Step count at which `check_limits` runs next, zero makes the next step run it.
+    next_check: u64,
+    max_steps: Option<u64>,
+    max_loop_depth: usize,
+    #[cfg(feature = "std")]
+    started_at: Option<Instant>,
+    #[cfg(feature = "std")]
+    timeout: Option<Duration>,
+    breakpoint_handler: Option<BreakpointHandler<C>>,
+    profiling: bool,
+    profile: Vec<u64>,
+    optimizations: Optimizations,
+    breakpoints: BTreeSet<usize>,
+    // Where `run_until_breakpoint` last stopped, as instruction index and step count, so resuming from
+    // there runs that instruction instead of stopping on it again.
+    breakpoint_stop: Option<(usize, u64)>,
+    input: Option<Cursor<Vec<u8>>>,
+    captured_output: Option<Vec<u8>>,
+}
+
+/// Called with the interpreter's state whenever a `#` breakpoint runs.
+pub type BreakpointHandler<C> = Box<dyn FnMut(&Interpreter<C>)>;
+
+impl<C: Cell> Default for Interpreter<C> {
+    fn default() -> Self {
+        Self::with_cells(RAM_SIZE)
+    }
 }
 
 impl Interpreter {
+    /// Creates an interpreter with a fixed tape of 100_000 cells.
     pub fn new() -> Interpreter {
+        Interpreter::with_tape_size(RAM_SIZE)
+    }
+
+    /// Alias of `with_tape_size`.
+    pub fn with_ram_size(size: usize) -> Interpreter {
+        Interpreter::with_tape_size(size)
+    }
+
+    /// Creates an interpreter with a fixed tape of `size` cells, e.g. 30_000 for the classic spec.
+    pub fn with_tape_size(size: usize) -> Interpreter {
+        Interpreter::with_cells(size)
+    }
+}
+
+impl<C: Cell> Interpreter<C> {
+    /// Creates an interpreter with a fixed tape of `size` cells of type `C`,
+    /// e.g. `Interpreter::<u16>::with_cells(30_000)` for 16-bit cells.
+    pub fn with_cells(size: usize) -> Interpreter<C> {
         Interpreter {
             memory_pointer: 0,
             instruction_pointer: 0,
-            ram: [0x0; RAM_SIZE],
+            ram: vec![C::ZERO; size],
+            tape_mode: TapeMode::Fixed(size),
+            pointer_mode: PointerMode::default(),
+            max_ram_size: None,
             program: Vec::new(),
-            jump_map: HashMap::new(),
-            lexer: Lexer::new(),
+            debug_breakpoints: false,
+            eof_behavior: EofBehavior::default(),
+            output_mode: OutputMode::default(),
+            interactive: true,
+            steps: 0,
+            next_check: 0,
+            max_steps: None,
+            max_loop_depth: DEFAULT_MAX_LOOP_DEPTH,
+            #[cfg(feature = "std")]
+            started_at: None,
+            #[cfg(feature = "std")]
+            timeout: None,
+            breakpoint_handler: None,
+            profiling: false,
+            profile: Vec::new(),
+            optimizations: Optimizations::default(),
+            breakpoints: BTreeSet::new(),
+            breakpoint_stop: None,
+            input: None,
+            captured_output: None,
         }
     }
 
-    pub fn load_program_from_file(&mut self, program_path: &str) {
-        let mut program_file = File::open(program_path).expect("[ERROR] Unable to open the file !");
+    pub fn set_tape_mode(&mut self, tape_mode: TapeMode) {
+        if let TapeMode::Fixed(size) = tape_mode {
+            self.ram.resize(size, C::ZERO);
+        }
 
-        let mut program_buffer = String::new();
+        self.tape_mode = tape_mode;
+        self.next_check = 0;
+    }
 
-        program_file.read_to_string(&mut program_buffer).expect("[ERROR] Unable to read the file !");
+    /// Set it before loading the program, as `PointerMode::Clamp` changes how it is compiled.
+    pub fn set_pointer_mode(&mut self, pointer_mode: PointerMode) {
+        self.pointer_mode = pointer_mode;
+    }
 
-        self.lexer.fill(program_buffer.as_str());
+    /// Caps how far a `TapeMode::Growable` tape may grow, so runaway programs fail with
+    /// `PointerOverflow` instead of eating all the memory. `None` (the default) means no limit.
+    pub fn set_max_ram_size(&mut self, max_ram_size: Option<usize>) {
+        self.max_ram_size = max_ram_size;
+    }
 
-        let mut c = self.lexer.next();
+    pub fn set_eof_behavior(&mut self, eof_behavior: EofBehavior) {
+        self.eof_behavior = eof_behavior;
+    }
 
-        while c != '@' {
-            let ir_inst: IRInstruction;
-            let inst_kind: IRInstructionKind;
+    pub fn set_output_mode(&mut self, output_mode: OutputMode) {
+        self.output_mode = output_mode;
+    }
 
-            match c {
-                '>' | '<' | '+' | '-' => {
-                    if c == '>' { inst_kind = IRInstructionKind::IncrementPointer; }
-                    else if c == '<' { inst_kind = IRInstructionKind::DecrementPointer; }
-                    else if c == '+' { inst_kind = IRInstructionKind::IncrementByte; }
-                    else { inst_kind = IRInstructionKind::DecrementByte; }
+    /// When on (the default), `.` flushes the output after every byte. When off, output is buffered and
+    /// only flushed before `,` reads, so prompts still show, and when `interpret` or `run_until_breakpoint`
+    /// return. Much faster for programs printing a lot.
+    pub fn set_interactive(&mut self, interactive: bool) {
+        self.interactive = interactive;
+    }
 
-                    let mut streak = 1u8;
-                    let mut s = self.lexer.next();
+    /// Applies to programs loaded afterwards. `Optimizations::NONE` keeps the plain run-length folded IR.
+    pub fn set_optimizations(&mut self, optimizations: Optimizations) {
+        self.optimizations = optimizations;
+    }
 
-                    while c == s {
-                        streak += 1;
-                        s = self.lexer.next();
-                    }
+    /// Makes the lexer treat `#` as a breakpoint instead of a comment, for programs loaded afterwards.
+    pub fn set_debug_breakpoints(&mut self, enabled: bool) {
+        self.debug_breakpoints = enabled;
+    }
 
-                    ir_inst = IRInstruction { kind: inst_kind, operand: Some(streak) };
+    /// Alias of `set_debug_breakpoints`.
+    pub fn set_allow_debug_instruction(&mut self, enabled: bool) {
+        self.set_debug_breakpoints(enabled);
+    }
 
-                    c = s;
-                },
-                '.' | ',' | '[' | ']' => {
-                    if c == '.' { inst_kind = IRInstructionKind::PrintByteAsChar; }
-                    else if c == ',' { inst_kind = IRInstructionKind::ReadInputToByte; }
-                    else if c == '[' { inst_kind = IRInstructionKind::JumpIfZero; }
-                    else { inst_kind = IRInstructionKind::JumpIfNotZero; }
+    /// Sets the callback run at each breakpoint, typically to inspect the tape around the pointer.
+    /// Without one, breakpoints write the cells around the pointer to the output instead,
+    /// on their own line, e.g. `# pointer 5, cells 1..10: 0 0 3 0 [72] 0 0 0 0`.
+    pub fn set_breakpoint_handler(&mut self, handler: impl FnMut(&Interpreter<C>) + 'static) {
+        self.breakpoint_handler = Some(Box::new(handler));
+    }
 
-                    ir_inst = IRInstruction { kind: inst_kind, operand: None };
+    /// Counts how many times each IR instruction runs, see `profile_report`.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profiling = enabled;
+        self.size_profile();
+    }
 
-                    c = self.lexer.next();
-                },
-                _ => continue,
+    /// Shorthand for `set_profiling(true)`.
+    pub fn enable_profiling(&mut self) {
+        self.set_profiling(true);
+    }
+
+    /// The profile gathered since the last `reset`, summed per instruction kind.
+    pub fn profile(&self) -> Profile {
+        let mut profile = Profile::default();
+
+        for (inst, &count) in self.program.iter().zip(self.profile.iter()).filter(|&(_, &count)| count > 0) {
+            match profile.counts.iter_mut().find(|(kind, _)| *kind == inst.kind) {
+                Some((_, total)) => *total += count,
+                None => profile.counts.push((inst.kind, count)),
+            }
+
+            if inst.kind == IRInstructionKind::JumpIfNotZero {
+                profile.loop_iterations += count;
             }
-            self.program.push(ir_inst);
+
+            profile.total_cycles += count;
+        }
+
+        profile.counts.sort_by_key(|&(_, count)| Reverse(count));
+
+        profile
+    }
+
+    /// Execution count of every IR instruction as `(instruction index, count)`, hottest first.
+    /// Counts are per IR instruction, so a folded run like `+++++` or a clear loop counts once per execution.
+    pub fn profile_report(&self) -> Vec<(usize, u64)> {
+        let mut report: Vec<(usize, u64)> = self.profile.iter().copied().enumerate().collect();
+
+        report.sort_by_key(|&(_, count)| Reverse(count));
+
+        report
+    }
+
+    /// Stops execution with `SacError::StepLimit` once `max` IR instructions ran.
+    /// Steps are counted per IR instruction, so a folded run like `+++++` counts as one step.
+    pub fn set_max_steps(&mut self, max: u64) {
+        self.max_steps = Some(max);
+        self.next_check = 0;
+    }
+
+    /// Makes loading fail with `SacError::LoopTooDeep` when loops nest deeper than `depth`, 256 by default.
+    /// Guards against pathological generated code, e.g. before transpiling it.
+    pub fn set_max_loop_depth(&mut self, depth: usize) {
+        self.max_loop_depth = depth;
+    }
+
+    /// Alias of `set_max_steps`, one cycle being one executed IR instruction.
+    pub fn set_max_cycles(&mut self, limit: u64) {
+        self.set_max_steps(limit);
+    }
+
+    /// Stops execution with `SacError::Timeout` once `duration` elapsed since the first step.
+    /// The clock is only read every 4096 steps, so the limit can be overshot by up to that many instructions.
+    #[cfg(feature = "std")]
+    pub fn set_timeout(&mut self, duration: Duration) {
+        self.timeout = Some(duration);
+        self.next_check = 0;
+    }
+
+    /// Alias of `set_timeout`, exceeding it fails with `SacError::Timeout`.
+    #[cfg(feature = "std")]
+    pub fn set_time_limit(&mut self, duration: Duration) {
+        self.set_timeout(duration);
+    }
+
+    /// Zeroes the tape and rewinds both pointers so the loaded program can run again from the start.
+    /// Configuration such as the tape or EOF modes is kept.
+    pub fn reset(&mut self) {
+        self.memory_pointer = 0;
+        self.instruction_pointer = 0;
+        self.ram.fill(C::ZERO);
+        self.steps = 0;
+        self.next_check = 0;
+        self.breakpoint_stop = None;
+        #[cfg(feature = "std")]
+        {
+            self.started_at = None;
+        }
+        self.profile.clear();
+        self.size_profile();
+
+        if let Some(input) = self.input.as_mut() {
+            input.set_position(0);
         }
     }
 
-    fn precompute_jumps(&mut self) {
-        let mut stack = Vec::<usize>::new();
+    /// Like `reset`, but also drops the loaded program so a different one can be loaded.
+    pub fn clear(&mut self) {
+        self.reset();
+        self.program.clear();
+        self.size_profile();
+    }
+
+    pub fn memory_pointer(&self) -> usize {
+        self.memory_pointer
+    }
+
+    pub fn instruction_pointer(&self) -> usize {
+        self.instruction_pointer
+    }
+
+    /// The loaded IR in execution order, as optimized by `parse`.
+    pub fn instructions(&self) -> impl Iterator<Item = &IRInstruction> {
+        self.program.iter()
+    }
+
+    /// Value of the cell at `index`, or zero past the end of the tape.
+    pub fn cell(&self, index: usize) -> C {
+        self.cell_at(index).unwrap_or(C::ZERO)
+    }
+
+    /// Value of the cell at `index`, or `None` past the end of the tape.
+    pub fn cell_at(&self, index: usize) -> Option<C> {
+        self.ram.get(index).copied()
+    }
+
+    pub fn tape(&self) -> &[C] {
+        &self.ram
+    }
+
+    /// The cells in `range`, clipped to the tape.
+    pub fn tape_slice(&self, range: Range<usize>) -> &[C] {
+        let end = range.end.min(self.ram.len());
+        let start = range.start.min(end);
+
+        &self.ram[start..end]
+    }
+
+    #[cfg(feature = "std")]
+    pub fn load_program_from_file(&mut self, program_path: &str) -> Result<(), SacError> {
+        let program_file = File::open(program_path)?;
+
+        self.load_program_from_reader(BufReader::new(program_file))
+    }
+
+    pub fn load_program_from_str(&mut self, code: &str) -> Result<(), SacError> {
+        self.load_program_from_reader(code.as_bytes())
+    }
+
+    /// Loads a program written as `source!input`: the code before the first `!` is compiled and the bytes
+    /// after it become the program's input, as with `set_input`. Without a `!` the input is empty.
+    pub fn load_program_with_inline_input(&mut self, code: &str) -> Result<(), SacError> {
+        let (source, input) = code.split_once('!').unwrap_or((code, ""));
+
+        self.set_input(input.as_bytes().to_vec());
+
+        self.load_program_from_str(source)
+    }
+
+    /// Streams the source from `reader` and folds it into IR appended to the program as it goes,
+    /// so only the IR, not the source text, is kept in memory. `reader` is read to its end, so when it is
+    /// stdin the program's own `,` input finds stdin already exhausted.
+    pub fn load_program_from_reader<R: BufRead>(&mut self, reader: R) -> Result<(), SacError> {
+        let mut lexer = Lexer::new(reader, self.debug_breakpoints);
+        let mut program = Vec::new();
+
+        build_ir(&mut lexer, &mut program)?;
+
+        // Only the new IR is optimized, and it is checked before appending so a failed load leaves
+        // the loaded program as it was. Passes never look across the boundary between the two.
+        let at_start = self.program.is_empty() && self.instruction_pointer == 0 && self.memory_pointer == 0 && self.ram.iter().all(|&cell| cell == C::ZERO);
+
+        self.optimize_ir(&mut program, at_start)?;
+
+        self.program.append(&mut program);
+        precompute_jumps(&mut self.program);
+        self.size_profile();
+
+        Ok(())
+    }
+
+    /// Re-optimizes the whole loaded program from its current IR, e.g. after `set_optimizations`, and
+    /// resolves its jumps. Instruction indices change, so the interpreter is rewound like `reset` does.
+    /// The `load_program_*` methods already optimize what they load.
+    pub fn parse(&mut self) -> Result<(), SacError> {
+        let mut program = self.program.clone();
 
-        let mut local_instruction_pointer = 0usize;
+        self.reset();
+        self.optimize_ir(&mut program, true)?;
+        self.program = program;
+        self.size_profile();
 
-        while local_instruction_pointer < self.program.len() {
-            let inst = self.program[local_instruction_pointer];
+        Ok(())
+    }
+
+    // Checks and optimizes `program` on its own, as run from a zeroed tape when `at_start` is set.
+    fn optimize_ir(&self, program: &mut Vec<IRInstruction>, at_start: bool) -> Result<(), SacError> {
+        let mut optimizations = self.optimizations;
+        let mut depth = 0usize;
 
+        for inst in program.iter() {
             match inst.kind {
-                IRInstructionKind::JumpIfZero => stack.push(local_instruction_pointer),
-                IRInstructionKind::JumpIfNotZero => {
-                    let target = stack.pop().unwrap();
-                    self.jump_map.insert(local_instruction_pointer, target);
-                    self.jump_map.insert(target, local_instruction_pointer);
+                IRInstructionKind::JumpIfZero => {
+                    depth += 1;
+
+                    if depth > self.max_loop_depth {
+                        return Err(SacError::LoopTooDeep { max_depth: self.max_loop_depth, span: inst.span });
+                    }
                 },
-                _ => (), // Other instructions aren't jump related.
+                IRInstructionKind::JumpIfNotZero => depth = depth.saturating_sub(1),
+                _ => (),
+            }
+        }
+
+        // Moves don't add up there: clamped moves stop at the edges, and on a growable tape `<` wraps
+        // to the end while `>` grows it.
+        let moves_add_up = match self.pointer_mode {
+            PointerMode::Error => true,
+            PointerMode::Wrap => self.tape_mode != TapeMode::Growable,
+            PointerMode::Clamp => false,
+        };
+
+        if !moves_add_up {
+            optimizations.cancelling_runs = false;
+            optimizations.offsets = false;
+            optimizations.multiply_loops = false;
+        }
+
+        optimize::<C>(program, optimizations, self.ram.len(), at_start)
+    }
+
+    /// Replaces the loaded program with `program`, typically the output of `compile`, resolves its jumps
+    /// and rewinds the interpreter so it runs from the start. Unbalanced jumps fail with `UnbalancedBrackets`,
+    /// an instruction missing its run length, factor or value with an `InvalidData` I/O error, and either
+    /// leaves the loaded program as it was.
+    pub fn load_program(&mut self, mut program: Vec<IRInstruction>) -> Result<(), SacError> {
+        if program.iter().any(|inst| inst.kind.has_operand() && inst.operand.is_none()) {
+            return Err(SacError::Io(io::Error::new(io::ErrorKind::InvalidData, "IR instruction without its operand")));
+        }
+
+        validate_brackets(&program)?;
+        precompute_jumps(&mut program);
+
+        self.reset();
+        self.program = program;
+        self.size_profile();
+
+        Ok(())
+    }
+
+    // The checks a step only needs now and then: the step limit, the timeout, and the pointer being off
+    // a tape that was resized or restored under it. Schedules the next run through `next_check`.
+    #[cold]
+    #[inline(never)]
+    fn check_limits(&mut self) -> Result<(), SacError> {
+        if let Some(max_steps) = self.max_steps.filter(|&max| self.steps >= max) {
+            return Err(SacError::StepLimit { max_steps });
+        }
+
+        if self.memory_pointer >= self.ram.len() {
+            // Only on a zero-cell tape, or one shrunk under the pointer: grow, wrap or clamp back
+            // onto it as configured, or fail with a `TapeError` rather than panic on the current cell.
+            self.memory_pointer = self.cell_index(0)?;
+        }
+
+        self.next_check = self.max_steps.unwrap_or(u64::MAX);
+
+        #[cfg(feature = "std")]
+        if let Some(timeout) = self.timeout {
+            let started_at = *self.started_at.get_or_insert_with(Instant::now);
+
+            if started_at.elapsed() > timeout {
+                return Err(SacError::Timeout { timeout });
+            }
+
+            let next_poll = (self.steps / TIMEOUT_POLL_INTERVAL + 1) * TIMEOUT_POLL_INTERVAL;
+
+            self.next_check = self.next_check.min(next_poll);
+        }
+
+        Ok(())
+    }
+
+    // Keeps one counter per loaded instruction while profiling, so counting a step is a plain increment.
+    fn size_profile(&mut self) {
+        if self.profiling {
+            self.profile.resize(self.program.len(), 0);
+        }
+    }
+
+    // Off the per-step path: a run without profiling only pays for the flag check.
+    #[cold]
+    #[inline(never)]
+    fn count_step(&mut self) {
+        self.profile[self.instruction_pointer] += 1;
+    }
+
+    /// Flags the loops of the loaded program that are sure to spin forever once entered: loops without
+    /// inner loops or input whose body moves the pointer back where it started and has no net effect on that cell,
+    /// like `+[>+<]`. Loops it cannot reason about are never flagged.
+    pub fn analyze_loops(&self) -> Vec<LoopWarning> {
+        let mut warnings = Vec::new();
+
+        for (open, inst) in self.program.iter().enumerate().filter(|(_, inst)| inst.kind == IRInstructionKind::JumpIfZero) {
+            let close = inst.operand.unwrap();
+
+            if Self::loop_never_changes_its_cell(&self.program[open + 1..close]) {
+                warnings.push(LoopWarning { open, close, span: inst.span });
             }
+        }
+
+        warnings
+    }
+
+    fn loop_never_changes_its_cell(body: &[IRInstruction]) -> bool {
+        let mut position = 0isize;
+        let mut delta = 0i128;
 
-            local_instruction_pointer += 1;
+        for inst in body {
+            let on_loop_cell = position + inst.offset == 0;
+
+            match inst.kind {
+                IRInstructionKind::IncrementPointer => position += inst.operand.unwrap() as isize,
+                IRInstructionKind::DecrementPointer => position -= inst.operand.unwrap() as isize,
+                IRInstructionKind::IncrementByte if on_loop_cell => delta += inst.operand.unwrap() as i128,
+                IRInstructionKind::DecrementByte if on_loop_cell => delta -= inst.operand.unwrap() as i128,
+                IRInstructionKind::SetZero | IRInstructionKind::SetCell | IRInstructionKind::MultiplyAdd if on_loop_cell => return false,
+                IRInstructionKind::IncrementByte
+                | IRInstructionKind::DecrementByte
+                | IRInstructionKind::SetZero
+                | IRInstructionKind::SetCell
+                | IRInstructionKind::MultiplyAdd
+                | IRInstructionKind::PrintByteAsChar
+                | IRInstructionKind::Breakpoint => (),
+                IRInstructionKind::ReadInputToByte
+                | IRInstructionKind::JumpIfZero
+                | IRInstructionKind::JumpIfNotZero
+                | IRInstructionKind::ScanRight
+                | IRInstructionKind::ScanLeft => return false,
+            }
         }
+
+        position == 0 && delta == 0
     }
 
-    pub fn interpret(&mut self) {
-        self.precompute_jumps();
+    /// Checks the brackets of the loaded program and measures it, without touching the tape.
+    pub fn validate(&self) -> Result<ProgramStats, BracketError> {
+        validate_brackets(&self.program)?;
 
-        while self.instruction_pointer < self.program.len() {
-            let inst = self.program[self.instruction_pointer];
+        let mut stats = ProgramStats { instruction_count: self.program.len(), ..ProgramStats::default() };
+        let mut depth = 0usize;
 
+        for inst in self.program.iter() {
             match inst.kind {
-                IRInstructionKind::IncrementPointer => self.memory_pointer += inst.operand.unwrap() as usize,
-                IRInstructionKind::DecrementPointer => self.memory_pointer -= inst.operand.unwrap() as usize,
-                IRInstructionKind::IncrementByte => self.ram[self.memory_pointer] += inst.operand.unwrap(),
-                IRInstructionKind::DecrementByte => self.ram[self.memory_pointer] -= inst.operand.unwrap(),
-                IRInstructionKind::PrintByteAsChar => {
-                    let byte_as_char = self.ram[self.memory_pointer] as char;
-                    print!("{byte_as_char}");
-                    io::stdout().flush().unwrap();
-                },
-                IRInstructionKind::ReadInputToByte => {
-                    let mut input: [u8; 1] = [0; 1];
-                    io::stdin().read_exact(&mut input).expect("[ERROR] Unable to read stdin.");
-                    self.ram[self.memory_pointer] = input[0];
-                },
                 IRInstructionKind::JumpIfZero => {
-                    if self.ram[self.memory_pointer] == 0 {
-                        self.instruction_pointer = *self.jump_map.get(&self.instruction_pointer).unwrap();
-                    }
+                    stats.loop_count += 1;
+                    depth += 1;
+                    stats.max_nesting_depth = stats.max_nesting_depth.max(depth);
                 },
-                IRInstructionKind::JumpIfNotZero => {
-                    if self.ram[self.memory_pointer] != 0 {
-                        self.instruction_pointer = *self.jump_map.get(&self.instruction_pointer).unwrap();
-                    }
-                }
+                IRInstructionKind::JumpIfNotZero => depth -= 1,
+                _ => (),
             }
+        }
+
+        Ok(stats)
+    }
+
+    // The cell under the memory pointer. The pointer only ends up off the tape when the tape was shrunk
+    // with `set_tape_mode` or a mismatched `State` was restored, which is a caller bug, so this panics.
+    fn current_cell(&self) -> C {
+        match self.ram.get(self.memory_pointer) {
+            Some(&cell) => cell,
+            None => self.pointer_off_tape(),
+        }
+    }
 
-            self.instruction_pointer += 1;
+    fn current_cell_mut(&mut self) -> &mut C {
+        if self.memory_pointer >= self.ram.len() {
+            self.pointer_off_tape();
         }
+
+        &mut self.ram[self.memory_pointer]
+    }
+
+    fn pointer_off_tape(&self) -> ! {
+        panic!(
+            "memory pointer {} is off the {}-cell tape at instruction {} ({})",
+            self.memory_pointer,
+            self.ram.len(),
+            self.instruction_pointer,
+            self.program[self.instruction_pointer].kind
+        );
     }
+
+    fn write_debug_dump<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let start = self.memory_pointer.saturating_sub(DEBUG_DUMP_RADIUS);
+        let end = (self.memory_pointer + DEBUG_DUMP_RADIUS + 1).min(self.ram.len());
+
+        write!(output, "\n# pointer {}, cells {start}..{end}:", self.memory_pointer)?;
+
+        for (index, cell) in (start..end).zip(self.tape_slice(start..end)) {
+            if index == self.memory_pointer {
+                write!(output, " [{cell}]")?;
+            } else {
+                write!(output, " {cell}")?;
+            }
+        }
+
+        writeln!(output)?;
+        output.flush()
+    }
+
+    /// Resolves the cell `offset` cells away from the memory pointer, growing or wrapping the tape as configured.
+    #[inline]
+    fn cell_index(&mut self, offset: isize) -> Result<usize, TapeError> {
+        if let Some(index) = self.memory_pointer.checked_add_signed(offset) {
+            if index < self.ram.len() {
+                return Ok(index);
+            }
+        }
+
+        self.cell_index_off_tape(offset)
+    }
+
+    // `cell_index` for a cell past either end of the tape.
+    #[cold]
+    #[inline(never)]
+    fn cell_index_off_tape(&mut self, offset: isize) -> Result<usize, TapeError> {
+        let index = match self.memory_pointer.checked_add_signed(offset) {
+            Some(index) => index,
+            None if self.pointer_mode == PointerMode::Wrap && !self.ram.is_empty() => return Ok(self.wrapped_index(offset)),
+            None if self.pointer_mode == PointerMode::Clamp && !self.ram.is_empty() => return Ok(0),
+            None => return Err(TapeError::PointerUnderflow { instruction_pointer: self.instruction_pointer, span: self.program[self.instruction_pointer].span }),
+        };
+
+        if index < self.ram.len() || self.grow_tape_to(index) {
+            return Ok(index);
+        }
+
+        if self.pointer_mode == PointerMode::Wrap && self.tape_mode != TapeMode::Growable && !self.ram.is_empty() {
+            return Ok(self.wrapped_index(offset));
+        }
+
+        if self.pointer_mode == PointerMode::Clamp && !self.ram.is_empty() {
+            return Ok(self.ram.len() - 1);
+        }
+
+        Err(TapeError::PointerOverflow { instruction_pointer: self.instruction_pointer, span: self.program[self.instruction_pointer].span })
+    }
+
+    fn wrapped_index(&self, offset: isize) -> usize {
+        (self.memory_pointer as i128 + offset as i128).rem_euclid(self.ram.len() as i128) as usize
+    }
+
+    fn grow_tape_to(&mut self, index: usize) -> bool {
+        if self.tape_mode != TapeMode::Growable || self.max_ram_size.is_some_and(|max| index >= max) {
+            return false;
+        }
+
+        let mut new_size = (index + 1).max(self.ram.len() * 2);
+
+        if let Some(max) = self.max_ram_size {
+            new_size = new_size.min(max);
+        }
+
+        self.ram.resize(new_size, C::ZERO);
+
+        true
+    }
+
+    /// Makes `interpret`, `step` and `run_until_breakpoint` read the program's input from `bytes` instead
+    /// of stdin. Reading past the end behaves like stdin at EOF, and `reset` rewinds to the start of `bytes`.
+    pub fn set_input(&mut self, bytes: Vec<u8>) {
+        self.input = Some(Cursor::new(bytes));
+    }
+
+    /// Makes `interpret`, `step` and `run_until_breakpoint` buffer the program's output instead of
+    /// writing it to stdout, see `take_output`.
+    pub fn enable_output_capture(&mut self) {
+        self.captured_output.get_or_insert_with(Vec::new);
+    }
+
+    /// Returns the output captured so far and empties the buffer, or nothing when capture is off.
+    pub fn take_output(&mut self) -> Vec<u8> {
+        self.captured_output.as_mut().map(core::mem::take).unwrap_or_default()
+    }
+
+    // Runs `f` with the input set by `set_input` (or stdin) and the capture buffer (or stdout).
+    // Without the `std` feature there is no stdin or stdout, see `io::default_input`.
+    fn with_default_io<T>(&mut self, f: impl FnOnce(&mut Self, &mut dyn Read, &mut dyn Write) -> T) -> T {
+        let mut stdin = None;
+        let mut stdout = None;
+        let mut input = self.input.take();
+        let mut captured_output = self.captured_output.take();
+
+        let input_stream: &mut dyn Read = match input.as_mut() {
+            Some(input) => input,
+            None => stdin.insert(io::default_input()),
+        };
+
+        let output_stream: &mut dyn Write = match captured_output.as_mut() {
+            Some(captured_output) => captured_output,
+            None => stdout.insert(io::default_output()),
+        };
+
+        let result = f(self, input_stream, output_stream);
+
+        self.input = input;
+        self.captured_output = captured_output;
+
+        result
+    }
+
+    /// Runs the loaded program to completion, see `interpret_with_io`.
+    pub fn interpret(&mut self) -> Result<u64, SacError> {
+        self.with_default_io(|interpreter, mut input, mut output| interpreter.interpret_with_io(&mut input, &mut output))
+    }
+
+    /// Runs the loaded program to completion and returns the number of IR instructions executed since
+    /// the last `reset`, as counted by `set_max_steps`. Pure execution: the program must already be parsed.
+    pub fn interpret_with_io<R: Read, W: Write>(&mut self, input: &mut R, output: &mut W) -> Result<u64, SacError> {
+        let result = (|| {
+            loop {
+                self.run_on_tape();
+
+                if self.step_with_io(input, output)?.is_none() {
+                    return Ok(self.steps);
+                }
+            }
+        })();
+
+        flush_after(output, result)
+    }
+
+    // The hot loop of `interpret_with_io`. Runs the instructions that stay on the tape with the
+    // pointers held in locals, and stops before anything it doesn't handle: I/O, breakpoints, a cell
+    // off the tape, a due `check_limits` or profiling. `step_with_io` then runs that step.
+    fn run_on_tape(&mut self) {
+        if self.profiling || self.memory_pointer >= self.ram.len() {
+            return;
+        }
+
+        let program = &self.program[..];
+        let ram = &mut self.ram[..];
+        let mut instruction_pointer = self.instruction_pointer;
+        let mut memory_pointer = self.memory_pointer;
+        let mut steps = self.steps;
+        let next_check = self.next_check;
+
+        while instruction_pointer < program.len() && steps < next_check {
+            let inst = program[instruction_pointer];
+            let on_tape = |offset: isize| memory_pointer.checked_add_signed(offset).filter(|&index| index < ram.len());
+
+            match inst.kind {
+                IRInstructionKind::IncrementPointer | IRInstructionKind::DecrementPointer => {
+                    let distance = inst.operand.unwrap() as isize;
+
+                    match on_tape(if inst.kind == IRInstructionKind::IncrementPointer { distance } else { -distance }) {
+                        Some(index) => memory_pointer = index,
+                        None => break,
+                    }
+                },
+                IRInstructionKind::IncrementByte | IRInstructionKind::DecrementByte | IRInstructionKind::SetZero | IRInstructionKind::SetCell => {
+                    let Some(index) = on_tape(inst.offset) else { break };
+
+                    ram[index] = match inst.kind {
+                        IRInstructionKind::IncrementByte => ram[index].wrapping_add_operand(inst.operand.unwrap()),
+                        IRInstructionKind::DecrementByte => ram[index].wrapping_sub_operand(inst.operand.unwrap()),
+                        IRInstructionKind::SetZero => C::ZERO,
+                        _ => C::ZERO.wrapping_add_operand(inst.operand.unwrap()),
+                    };
+                },
+                IRInstructionKind::MultiplyAdd => {
+                    let value = ram[memory_pointer];
+
+                    if value != C::ZERO {
+                        let Some(index) = on_tape(inst.offset) else { break };
+
+                        ram[index] = ram[index].wrapping_add_operand(value.to_operand().wrapping_mul(inst.operand.unwrap()));
+                    }
+                },
+                IRInstructionKind::JumpIfZero => {
+                    if ram[memory_pointer] == C::ZERO {
+                        instruction_pointer = inst.operand.unwrap();
+                    }
+                },
+                IRInstructionKind::JumpIfNotZero => {
+                    if ram[memory_pointer] != C::ZERO {
+                        instruction_pointer = inst.operand.unwrap();
+                    }
+                },
+                IRInstructionKind::ScanRight if ram[memory_pointer] != C::ZERO => {
+                    match ram[memory_pointer..].iter().position(|&cell| cell == C::ZERO) {
+                        Some(distance) => memory_pointer += distance,
+                        None => break,
+                    }
+                },
+                IRInstructionKind::ScanLeft if ram[memory_pointer] != C::ZERO => {
+                    match ram[..=memory_pointer].iter().rposition(|&cell| cell == C::ZERO) {
+                        Some(index) => memory_pointer = index,
+                        None => break,
+                    }
+                },
+                IRInstructionKind::ScanRight | IRInstructionKind::ScanLeft => (),
+                IRInstructionKind::PrintByteAsChar | IRInstructionKind::ReadInputToByte | IRInstructionKind::Breakpoint => break,
+            }
+
+            instruction_pointer += 1;
+            steps += 1;
+        }
+
+        self.instruction_pointer = instruction_pointer;
+        self.memory_pointer = memory_pointer;
+        self.steps = steps;
+    }
+
+    /// Number of IR instructions executed since the last `reset`.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Captures the tape, both pointers and the step count, e.g. to rewind a debugging session.
+    pub fn snapshot(&self) -> State<C> {
+        State {
+            tape: self.ram.clone(),
+            memory_pointer: self.memory_pointer,
+            instruction_pointer: self.instruction_pointer,
+            steps: self.steps,
+        }
+    }
+
+    pub fn restore(&mut self, state: &State<C>) {
+        self.ram.clone_from(&state.tape);
+        self.memory_pointer = state.memory_pointer;
+        self.instruction_pointer = state.instruction_pointer;
+        self.steps = state.steps;
+        self.next_check = 0;
+    }
+
+    pub fn step(&mut self) -> Result<Option<StepResult>, SacError> {
+        self.with_default_io(|interpreter, mut input, mut output| interpreter.step_with_io(&mut input, &mut output))
+    }
+
+    /// Pauses `run_until_breakpoint` before the IR instruction at `index` runs, see `disassemble` for the indices.
+    pub fn set_breakpoint(&mut self, index: usize) {
+        self.breakpoints.insert(index);
+    }
+
+    pub fn clear_breakpoint(&mut self, index: usize) {
+        self.breakpoints.remove(&index);
+    }
+
+    pub fn run_until_breakpoint(&mut self) -> Result<RunStop, SacError> {
+        self.with_default_io(|interpreter, mut input, mut output| interpreter.run_until_breakpoint_with_io(&mut input, &mut output))
+    }
+
+    /// Steps until the instruction pointer reaches a breakpoint, including one on the instruction it
+    /// starts at, or the program finishes. Calling it again resumes past the breakpoint it stopped at.
+    pub fn run_until_breakpoint_with_io<R: Read, W: Write>(&mut self, input: &mut R, output: &mut W) -> Result<RunStop, SacError> {
+        let result = (|| {
+            let resumed_from = self.breakpoint_stop.take();
+
+            while self.instruction_pointer < self.program.len() {
+                let here = (self.instruction_pointer, self.steps);
+
+                if self.breakpoints.contains(&self.instruction_pointer) && resumed_from != Some(here) {
+                    self.breakpoint_stop = Some(here);
+
+                    return Ok(RunStop::Breakpoint(self.instruction_pointer));
+                }
+
+                self.step_with_io(input, output)?;
+            }
+
+            Ok(RunStop::Halted)
+        })();
+
+        flush_after(output, result)
+    }
+
+    /// Executes the instruction at `instruction_pointer` and moves past it, or returns `None` once
+    /// the program is finished. Jumps are resolved when the program is loaded, so no preparation is needed.
+    pub fn step_with_io<R: Read, W: Write>(&mut self, input: &mut R, output: &mut W) -> Result<Option<StepResult>, SacError> {
+        if self.instruction_pointer >= self.program.len() {
+            return Ok(None);
+        }
+
+        if self.steps >= self.next_check {
+            self.check_limits()?;
+        }
+
+        self.steps += 1;
+
+        if self.profiling {
+            self.count_step();
+        }
+
+        let inst = self.program[self.instruction_pointer];
+
+        match inst.kind {
+            IRInstructionKind::IncrementPointer => self.memory_pointer = self.cell_index(inst.operand.unwrap() as isize)?,
+            IRInstructionKind::DecrementPointer => self.memory_pointer = self.cell_index(-(inst.operand.unwrap() as isize))?,
+            IRInstructionKind::IncrementByte => {
+                let index = self.cell_index(inst.offset)?;
+                self.ram[index] = self.ram[index].wrapping_add_operand(inst.operand.unwrap());
+            },
+            IRInstructionKind::DecrementByte => {
+                let index = self.cell_index(inst.offset)?;
+                self.ram[index] = self.ram[index].wrapping_sub_operand(inst.operand.unwrap());
+            },
+            IRInstructionKind::PrintByteAsChar => {
+                match self.output_mode {
+                    OutputMode::Char => output.write_all(&[self.current_cell().low_byte()])?,
+                    OutputMode::Decimal => write!(output, "{} ", self.current_cell())?,
+                }
+
+                if self.interactive {
+                    output.flush()?;
+                }
+            },
+            IRInstructionKind::ReadInputToByte => {
+                if !self.interactive {
+                    output.flush()?;
+                }
+
+                let mut byte: [u8; 1] = [0; 1];
+                let read = input.read(&mut byte)?;
+
+                if read == 0 {
+                    match self.eof_behavior {
+                        EofBehavior::Unchanged => (),
+                        EofBehavior::Zero => *self.current_cell_mut() = C::ZERO,
+                        EofBehavior::NegativeOne => *self.current_cell_mut() = C::MAX,
+                    }
+                } else {
+                    *self.current_cell_mut() = C::from_byte(byte[0]);
+                }
+            },
+            IRInstructionKind::JumpIfZero => {
+                if self.current_cell() == C::ZERO {
+                    self.instruction_pointer = inst.operand.unwrap();
+                }
+            },
+            IRInstructionKind::JumpIfNotZero => {
+                if self.current_cell() != C::ZERO {
+                    self.instruction_pointer = inst.operand.unwrap();
+                }
+            },
+            IRInstructionKind::SetZero => {
+                let index = self.cell_index(inst.offset)?;
+                self.ram[index] = C::ZERO;
+            },
+            IRInstructionKind::SetCell => {
+                let index = self.cell_index(inst.offset)?;
+                self.ram[index] = C::ZERO.wrapping_add_operand(inst.operand.unwrap());
+            },
+            IRInstructionKind::MultiplyAdd => {
+                let value = self.current_cell();
+
+                // The original loop never runs on a zero cell, so it must not touch its targets either.
+                if value != C::ZERO {
+                    let index = self.cell_index(inst.offset)?;
+                    self.ram[index] = self.ram[index].wrapping_add_operand(value.to_operand().wrapping_mul(inst.operand.unwrap()));
+                }
+            },
+            IRInstructionKind::ScanRight if self.current_cell() != C::ZERO => {
+                match self.ram[self.memory_pointer..].iter().position(|&cell| cell == C::ZERO) {
+                    Some(distance) => self.memory_pointer += distance,
+                    None => {
+                        // Step off the end like `>` would, the next step scans on from there.
+                        self.memory_pointer = self.cell_index((self.ram.len() - self.memory_pointer) as isize)?;
+                        return Ok(Some(StepResult { kind: inst.kind, produced_output: false }));
+                    },
+                }
+            },
+            IRInstructionKind::ScanLeft if self.current_cell() != C::ZERO => {
+                match self.ram[..=self.memory_pointer].iter().rposition(|&cell| cell == C::ZERO) {
+                    Some(index) => self.memory_pointer = index,
+                    None => {
+                        self.memory_pointer = self.cell_index(-(self.memory_pointer as isize) - 1)?;
+                        return Ok(Some(StepResult { kind: inst.kind, produced_output: false }));
+                    },
+                }
+            },
+            IRInstructionKind::ScanRight | IRInstructionKind::ScanLeft => (),
+            IRInstructionKind::Breakpoint => {
+                // Taken out for the call so the handler can borrow the whole interpreter.
+                if let Some(mut handler) = self.breakpoint_handler.take() {
+                    handler(self);
+                    self.breakpoint_handler = Some(handler);
+                } else {
+                    self.write_debug_dump(output)?;
+                }
+            },
+        }
+
+        self.instruction_pointer += 1;
+
+        Ok(Some(StepResult { kind: inst.kind, produced_output: inst.kind == IRInstructionKind::PrintByteAsChar }))
+    }
+}
+
+/// Merges adjacent pointer moves (`><`) and adjacent byte changes (`+-`) into their net effect,
+/// dropping them altogether when they cancel out.
+fn optimize_cancelling_runs(program: &mut Vec<IRInstruction>) {
+    let mut optimized: Vec<IRInstruction> = Vec::with_capacity(program.len());
+
+    for inst in program.iter() {
+        let merged = match (optimized.last().and_then(signed_run), signed_run(inst)) {
+            (Some((up, down, a)), Some((other_up, _, b))) if up == other_up => Some((up, down, a + b)),
+            _ => None,
+        };
+
+        match merged {
+            Some((up, down, net)) => {
+                let span = optimized.pop().unwrap().span;
+
+                if net > 0 {
+                    optimized.push(IRInstruction { kind: up, operand: Some(net as usize), offset: 0, span });
+                } else if net < 0 {
+                    optimized.push(IRInstruction { kind: down, operand: Some(net.unsigned_abs()), offset: 0, span });
+                }
+            },
+            None => optimized.push(*inst),
+        }
+    }
+
+    *program = optimized;
+}
+
+/// Splits a folded run into its (increasing kind, decreasing kind, signed amount).
+/// Runs applied away from the pointer (by `optimize_offsets`) are left alone.
+fn signed_run(inst: &IRInstruction) -> Option<(IRInstructionKind, IRInstructionKind, isize)> {
+    if inst.offset != 0 {
+        return None;
+    }
+
+    let amount = inst.operand? as isize;
+
+    match inst.kind {
+        IRInstructionKind::IncrementPointer => Some((IRInstructionKind::IncrementPointer, IRInstructionKind::DecrementPointer, amount)),
+        IRInstructionKind::DecrementPointer => Some((IRInstructionKind::IncrementPointer, IRInstructionKind::DecrementPointer, -amount)),
+        IRInstructionKind::IncrementByte => Some((IRInstructionKind::IncrementByte, IRInstructionKind::DecrementByte, amount)),
+        IRInstructionKind::DecrementByte => Some((IRInstructionKind::IncrementByte, IRInstructionKind::DecrementByte, -amount)),
+        _ => None,
+    }
+}
+
+/// Replaces `[-]` and `[+]` with a single `SetZero`, as both just zero the current cell.
+/// A run of `+` or `-` right after it is merged in, e.g. `[-]+++` becomes `SetCell(3)`
+/// and `[+]--` becomes `SetCell(-2)`, stored wrapping like `MultiplyAdd` factors.
+fn optimize_clear_loops(program: &mut Vec<IRInstruction>) {
+    let mut optimized = Vec::with_capacity(program.len());
+    let mut i = 0usize;
+
+    while i < program.len() {
+        if let Some(&[open, body, close]) = program.get(i..i + 3) {
+            let is_clear_loop = open.kind == IRInstructionKind::JumpIfZero
+                && (body.kind == IRInstructionKind::DecrementByte || body.kind == IRInstructionKind::IncrementByte)
+                && body.operand == Some(1)
+                && body.offset == 0
+                && close.kind == IRInstructionKind::JumpIfNotZero;
+
+            if is_clear_loop {
+                let value = match program.get(i + 3) {
+                    Some(next) if next.kind == IRInstructionKind::IncrementByte && next.offset == 0 => next.operand,
+                    Some(next) if next.kind == IRInstructionKind::DecrementByte && next.offset == 0 => next.operand.map(usize::wrapping_neg),
+                    _ => None,
+                };
+
+                match value {
+                    Some(value) => {
+                        optimized.push(IRInstruction { kind: IRInstructionKind::SetCell, operand: Some(value), offset: 0, span: open.span });
+                        i += 4;
+                    },
+                    None => {
+                        optimized.push(IRInstruction { kind: IRInstructionKind::SetZero, operand: None, offset: 0, span: open.span });
+                        i += 3;
+                    },
+                }
+
+                continue;
+            }
+        }
+
+        optimized.push(program[i]);
+        i += 1;
+    }
+
+    *program = optimized;
+}
+
+/// Turns runs of pointer moves and byte instructions (e.g. `>+<`) into byte instructions
+/// applied at an offset from the memory pointer, followed by one move for the net displacement.
+fn optimize_offsets(program: &mut Vec<IRInstruction>) {
+    let mut optimized = Vec::with_capacity(program.len());
+    let mut offset = 0isize;
+    let mut move_span = None;
+
+    for inst in program.iter() {
+        match inst.kind {
+            IRInstructionKind::IncrementPointer | IRInstructionKind::DecrementPointer => {
+                if inst.kind == IRInstructionKind::IncrementPointer {
+                    offset += inst.operand.unwrap() as isize;
+                } else {
+                    offset -= inst.operand.unwrap() as isize;
+                }
+
+                move_span.get_or_insert(inst.span);
+            },
+            IRInstructionKind::IncrementByte | IRInstructionKind::DecrementByte | IRInstructionKind::SetZero => {
+                optimized.push(IRInstruction { offset: inst.offset + offset, ..*inst });
+            },
+            _ => {
+                push_pointer_move(&mut optimized, offset, move_span.take().unwrap_or(inst.span));
+                offset = 0;
+                optimized.push(*inst);
+            },
+        }
+    }
+
+    push_pointer_move(&mut optimized, offset, move_span.unwrap_or_default());
+
+    *program = optimized;
+}
+
+/// Removes the loops that start on a cell known to be zero, so their body can never run: a loop at the
+/// very start of the program (the tape is all zeroes), or right after a `SetZero` of the current cell,
+/// another loop or a scan, which all leave the current cell at zero. Chains like `[-][a][b]` go as a whole.
+/// `at_start` tells whether `program` starts the program or is appended to one.
+fn optimize_dead_loops(program: &mut Vec<IRInstruction>, at_start: bool) {
+    let mut optimized: Vec<IRInstruction> = Vec::with_capacity(program.len());
+    let mut i = 0usize;
+
+    while i < program.len() {
+        let on_zero_cell = match optimized.last() {
+            None => at_start,
+            Some(prev) => match prev.kind {
+                IRInstructionKind::SetZero => prev.offset == 0,
+                IRInstructionKind::JumpIfNotZero | IRInstructionKind::ScanRight | IRInstructionKind::ScanLeft => true,
+                _ => false,
+            },
+        };
+
+        if program[i].kind == IRInstructionKind::JumpIfZero && on_zero_cell {
+            // Brackets are balanced, skip past the matching `]`.
+            let mut depth = 0usize;
+
+            loop {
+                match program[i].kind {
+                    IRInstructionKind::JumpIfZero => depth += 1,
+                    IRInstructionKind::JumpIfNotZero => depth -= 1,
+                    _ => (),
+                }
+
+                i += 1;
+
+                if depth == 0 {
+                    break;
+                }
+            }
+
+            continue;
+        }
+
+        optimized.push(program[i]);
+        i += 1;
+    }
+
+    *program = optimized;
+}
+
+/// Runs the start of the program at load time, up to the first I/O or breakpoint, and replaces it with a
+/// `SetCell` per non-zero cell it leaves followed by the final pointer move, e.g. `++++++++[>++++<-]>+`
+/// becomes `SetCell(33) @+1, IncrementPointer(1)`. Loops are folded as a whole or not at all, and folding
+/// stops before any move off the first `tape_len` cells or after `CONST_PREFIX_STEP_LIMIT` steps.
+/// The prefix is only replaced when that makes the program shorter.
+fn optimize_const_prefix<C: Cell>(program: &mut Vec<IRInstruction>, tape_len: usize) {
+    let mut tape = BTreeMap::<usize, C>::new();
+    let mut pointer = 0usize;
+    let mut budget = CONST_PREFIX_STEP_LIMIT;
+    let mut end = 0usize;
+
+    precompute_jumps(program);
+
+    while end < program.len() {
+        let next = match program[end].kind {
+            IRInstructionKind::JumpIfZero => program[end].operand.unwrap() + 1,
+            _ => end + 1,
+        };
+
+        if next - end == 1 {
+            // A single instruction gives up before touching the tape.
+            if run_const(program, end..next, &mut tape, &mut pointer, tape_len, &mut budget).is_none() {
+                break;
+            }
+        } else {
+            let mut loop_tape = tape.clone();
+            let mut loop_pointer = pointer;
+
+            if run_const(program, end..next, &mut loop_tape, &mut loop_pointer, tape_len, &mut budget).is_none() {
+                break;
+            }
+
+            tape = loop_tape;
+            pointer = loop_pointer;
+        }
+
+        end = next;
+    }
+
+    let span = program.first().map(|inst| inst.span).unwrap_or_default();
+    let mut folded = Vec::new();
+
+    for (&index, &value) in tape.iter().filter(|&(_, &value)| value != C::ZERO) {
+        folded.push(IRInstruction { kind: IRInstructionKind::SetCell, operand: Some(value.to_operand()), offset: index as isize, span });
+    }
+
+    push_pointer_move(&mut folded, pointer as isize, span);
+
+    if folded.len() < end {
+        program.splice(0..end, folded);
+    }
+}
+
+// Runs `program[range]` on a tape starting all zero, with resolved jumps. Gives up with `None` on I/O,
+// breakpoints, cells outside `0..tape_len` and once `budget` steps ran.
+fn run_const<C: Cell>(program: &[IRInstruction], range: Range<usize>, tape: &mut BTreeMap<usize, C>, pointer: &mut usize, tape_len: usize, budget: &mut usize) -> Option<()> {
+    let mut ip = range.start;
+
+    while ip < range.end {
+        *budget = budget.checked_sub(1)?;
+
+        let inst = program[ip];
+        let cell_at = |pointer: usize, offset: isize| pointer.checked_add_signed(offset).filter(|&index| index < tape_len);
+        let current = tape.get(pointer).copied().unwrap_or(C::ZERO);
+
+        match inst.kind {
+            IRInstructionKind::IncrementPointer => *pointer = cell_at(*pointer, inst.operand.unwrap() as isize)?,
+            IRInstructionKind::DecrementPointer => *pointer = cell_at(*pointer, -(inst.operand.unwrap() as isize))?,
+            IRInstructionKind::IncrementByte => {
+                let cell = tape.entry(cell_at(*pointer, inst.offset)?).or_insert(C::ZERO);
+                *cell = cell.wrapping_add_operand(inst.operand.unwrap());
+            },
+            IRInstructionKind::DecrementByte => {
+                let cell = tape.entry(cell_at(*pointer, inst.offset)?).or_insert(C::ZERO);
+                *cell = cell.wrapping_sub_operand(inst.operand.unwrap());
+            },
+            IRInstructionKind::SetZero => {
+                tape.insert(cell_at(*pointer, inst.offset)?, C::ZERO);
+            },
+            IRInstructionKind::SetCell => {
+                tape.insert(cell_at(*pointer, inst.offset)?, C::ZERO.wrapping_add_operand(inst.operand.unwrap()));
+            },
+            IRInstructionKind::MultiplyAdd if current != C::ZERO => {
+                let cell = tape.entry(cell_at(*pointer, inst.offset)?).or_insert(C::ZERO);
+                *cell = cell.wrapping_add_operand(current.to_operand().wrapping_mul(inst.operand.unwrap()));
+            },
+            IRInstructionKind::MultiplyAdd => (),
+            IRInstructionKind::JumpIfZero if current == C::ZERO => ip = inst.operand.unwrap(),
+            IRInstructionKind::JumpIfNotZero if current != C::ZERO => ip = inst.operand.unwrap(),
+            IRInstructionKind::JumpIfZero | IRInstructionKind::JumpIfNotZero => (),
+            IRInstructionKind::ScanRight | IRInstructionKind::ScanLeft => {
+                let step = if inst.kind == IRInstructionKind::ScanRight { 1 } else { -1 };
+                let mut scan = *pointer;
+
+                while tape.get(&scan).is_some_and(|&cell| cell != C::ZERO) {
+                    *budget = budget.checked_sub(1)?;
+                    scan = cell_at(scan, step)?;
+                }
+
+                *pointer = scan;
+            },
+            IRInstructionKind::PrintByteAsChar | IRInstructionKind::ReadInputToByte | IRInstructionKind::Breakpoint => return None,
+        }
+
+        ip += 1;
+    }
+
+    Some(())
+}
+
+/// Replaces `[>]` and `[<]` with a single `ScanRight` or `ScanLeft` searching the tape for a zero cell.
+fn optimize_scan_loops(program: &mut Vec<IRInstruction>) {
+    let mut optimized = Vec::with_capacity(program.len());
+    let mut i = 0usize;
+
+    while i < program.len() {
+        if let Some(&[open, body, close]) = program.get(i..i + 3) {
+            let scan_kind = match body.kind {
+                IRInstructionKind::IncrementPointer => Some(IRInstructionKind::ScanRight),
+                IRInstructionKind::DecrementPointer => Some(IRInstructionKind::ScanLeft),
+                _ => None,
+            };
+
+            let is_scan_loop = open.kind == IRInstructionKind::JumpIfZero
+                && body.operand == Some(1)
+                && close.kind == IRInstructionKind::JumpIfNotZero;
+
+            if let (true, Some(kind)) = (is_scan_loop, scan_kind) {
+                optimized.push(IRInstruction { kind, operand: None, offset: 0, span: open.span });
+                i += 3;
+                continue;
+            }
+        }
+
+        optimized.push(program[i]);
+        i += 1;
+    }
+
+    *program = optimized;
+}
+
+/// Turns copy/multiply loops such as `[->+>++<<]` into one `MultiplyAdd` per target cell
+/// followed by a `SetZero` of the loop cell. Only loops that (after `optimize_offsets`) contain
+/// nothing but byte arithmetic and decrement their own cell by exactly 1 qualify.
+fn optimize_multiply_loops(program: &mut Vec<IRInstruction>) {
+    let mut optimized = Vec::with_capacity(program.len());
+    let mut i = 0usize;
+
+    while i < program.len() {
+        if program[i].kind == IRInstructionKind::JumpIfZero {
+            if let Some((factors, end)) = multiply_loop_factors(program, i) {
+                let span = program[i].span;
+
+                for (offset, factor) in factors {
+                    optimized.push(IRInstruction { kind: IRInstructionKind::MultiplyAdd, operand: Some(factor as usize), offset, span });
+                }
+                optimized.push(IRInstruction { kind: IRInstructionKind::SetZero, operand: None, offset: 0, span });
+                i = end + 1;
+                continue;
+            }
+        }
+
+        optimized.push(program[i]);
+        i += 1;
+    }
+
+    *program = optimized;
+}
+
+/// Returns the non-zero `(offset, factor)` pairs of the multiply loop opening at `open`,
+/// along with the index of its closing bracket.
+fn multiply_loop_factors(program: &[IRInstruction], open: usize) -> Option<(Vec<(isize, isize)>, usize)> {
+    let mut factors = Vec::<(isize, isize)>::new();
+    let mut i = open + 1;
+
+    while let Some(inst) = program.get(i) {
+        let delta = match inst.kind {
+            IRInstructionKind::IncrementByte => inst.operand.unwrap() as isize,
+            IRInstructionKind::DecrementByte => -(inst.operand.unwrap() as isize),
+            IRInstructionKind::JumpIfNotZero => break,
+            _ => return None,
+        };
+
+        match factors.iter_mut().find(|(offset, _)| *offset == inst.offset) {
+            Some((_, factor)) => *factor += delta,
+            None => factors.push((inst.offset, delta)),
+        }
+
+        i += 1;
+    }
+
+    if i >= program.len() {
+        return None;
+    }
+
+    let counter = factors.iter().position(|&(offset, _)| offset == 0)?;
+
+    if factors.remove(counter).1 != -1 {
+        return None;
+    }
+
+    factors.retain(|&(_, factor)| factor != 0);
+
+    Some((factors, i))
+}
+
+fn push_pointer_move(program: &mut Vec<IRInstruction>, offset: isize, span: SourceSpan) {
+    if offset > 0 {
+        program.push(IRInstruction { kind: IRInstructionKind::IncrementPointer, operand: Some(offset as usize), offset: 0, span });
+    } else if offset < 0 {
+        program.push(IRInstruction { kind: IRInstructionKind::DecrementPointer, operand: Some(offset.unsigned_abs()), offset: 0, span });
+    }
+}
+
+fn build_ir<R: BufRead>(lexer: &mut Lexer<R>, program: &mut Vec<IRInstruction>) -> io::Result<()> {
+    let mut c = lexer.next()?;
+    let mut span = lexer.span();
+
+    while let Some(current) = c {
+        let ir_inst: IRInstruction;
+        let inst_kind: IRInstructionKind;
+
+        match current {
+            '>' | '<' | '+' | '-' => {
+                if current == '>' { inst_kind = IRInstructionKind::IncrementPointer; }
+                else if current == '<' { inst_kind = IRInstructionKind::DecrementPointer; }
+                else if current == '+' { inst_kind = IRInstructionKind::IncrementByte; }
+                else { inst_kind = IRInstructionKind::DecrementByte; }
+
+                let mut streak = 1usize;
+                let mut s = lexer.next()?;
+
+                while s == Some(current) {
+                    streak += 1;
+                    s = lexer.next()?;
+                }
+
+                ir_inst = IRInstruction { kind: inst_kind, operand: Some(streak), offset: 0, span };
+
+                c = s;
+            },
+            '.' | ',' | '[' | ']' | '#' => {
+                if current == '.' { inst_kind = IRInstructionKind::PrintByteAsChar; }
+                else if current == ',' { inst_kind = IRInstructionKind::ReadInputToByte; }
+                else if current == '[' { inst_kind = IRInstructionKind::JumpIfZero; }
+                else if current == ']' { inst_kind = IRInstructionKind::JumpIfNotZero; }
+                else { inst_kind = IRInstructionKind::Breakpoint; }
+
+                ir_inst = IRInstruction { kind: inst_kind, operand: None, offset: 0, span };
+
+                c = lexer.next()?;
+            },
+            _ => continue,
+        }
+        program.push(ir_inst);
+        span = lexer.span();
+    }
+
+    Ok(())
+}
+
+// Flushes what a non-interactive run buffered, even when it stopped on an error.
+fn flush_after<T, W: Write>(output: &mut W, result: Result<T, SacError>) -> Result<T, SacError> {
+    match result {
+        Ok(value) => {
+            output.flush()?;
+            Ok(value)
+        },
+        Err(e) => {
+            let _ = output.flush();
+            Err(e)
+        },
+    }
+}
+
+fn validate_brackets(program: &[IRInstruction]) -> Result<(), BracketError> {
+    let mut stack = Vec::<usize>::new();
+
+    for (index, inst) in program.iter().enumerate() {
+        match inst.kind {
+            IRInstructionKind::JumpIfZero => stack.push(index),
+            IRInstructionKind::JumpIfNotZero => {
+                stack.pop().ok_or(BracketError::UnmatchedClose { instruction_index: index, span: inst.span })?;
+            },
+            _ => (),
+        }
+    }
+
+    // The outermost unclosed '[' is the first one the user has to fix.
+    match stack.first() {
+        Some(&index) => Err(BracketError::UnmatchedOpen { instruction_index: index, span: program[index].span }),
+        None => Ok(()),
+    }
+}
+
+fn precompute_jumps(program: &mut [IRInstruction]) {
+    let mut stack = Vec::<usize>::new();
+
+    let mut local_instruction_pointer = 0usize;
+
+    while local_instruction_pointer < program.len() {
+        let inst = program[local_instruction_pointer];
+
+        match inst.kind {
+            IRInstructionKind::JumpIfZero => stack.push(local_instruction_pointer),
+            IRInstructionKind::JumpIfNotZero => {
+                let target = stack.pop().unwrap();
+                program[local_instruction_pointer].operand = Some(target);
+                program[target].operand = Some(local_instruction_pointer);
+            },
+            _ => (), // Other instructions aren't jump related.
+        }
+
+        local_instruction_pointer += 1;
+    }
+}
+
+/// The Brainfuck instructions of `code` in order, comments skipped, e.g. to build another front end.
+pub fn instructions(code: &str) -> impl Iterator<Item = char> + '_ {
+    let mut lexer = Lexer::new(code.as_bytes(), false);
+
+    // Reading from a byte slice can't fail.
+    core::iter::from_fn(move || lexer.next().ok().flatten())
+}
+
+/// Compiles `code` into optimized IR with resolved jumps, as `load_program_from_str` does with the
+/// default settings, without an interpreter. Feed the result to `Interpreter::load_program`.
+/// The `const_prefix` pass is left out: its values depend on the cell width and tape length of the
+/// interpreter, which the IR doesn't know yet.
+pub fn compile(code: &str) -> Result<Vec<IRInstruction>, SacError> {
+    let mut lexer = Lexer::new(code.as_bytes(), false);
+    let mut program = Vec::new();
+    let optimizations = Optimizations { const_prefix: false, ..Optimizations::default() };
+
+    build_ir(&mut lexer, &mut program)?;
+
+    optimize::<u8>(&mut program, optimizations, RAM_SIZE, true)?;
+
+    Ok(program)
+}
+
+// Checks the brackets of freshly built IR, then runs the enabled passes and resolves the jumps.
+// The cell type and tape length only matter to `optimize_const_prefix`, which runs the program.
+// Unless `at_start` is set, the IR is appended to a program and can't assume a zeroed tape.
+fn optimize<C: Cell>(program: &mut Vec<IRInstruction>, optimizations: Optimizations, tape_len: usize, at_start: bool) -> Result<(), SacError> {
+    validate_brackets(program)?;
+
+    if optimizations.cancelling_runs {
+        optimize_cancelling_runs(program);
+    }
+
+    if optimizations.clear_loops {
+        optimize_clear_loops(program);
+    }
+
+    if optimizations.offsets {
+        optimize_offsets(program);
+    }
+
+    if optimizations.multiply_loops {
+        optimize_multiply_loops(program);
+    }
+
+    if optimizations.scan_loops {
+        optimize_scan_loops(program);
+    }
+
+    if optimizations.dead_loops {
+        optimize_dead_loops(program, at_start);
+    }
+
+    if optimizations.const_prefix && at_start {
+        optimize_const_prefix::<C>(program, tape_len);
+    }
+
+    precompute_jumps(program);
+
+    Ok(())
+}
+
+/// Runs `code` on a default interpreter, feeding it `input`, and returns everything it printed.
+pub fn run(code: &str, input: &[u8]) -> Result<Vec<u8>, SacError> {
+    let mut interpreter = Interpreter::new();
+    let mut output = Vec::new();
+
+    interpreter.load_program_from_str(code)?;
+    interpreter.interpret_with_io(&mut &input[..], &mut output)?;
+
+    Ok(output)
+}
+
+/// Like `run`, with the output decoded as UTF-8. Output that isn't valid UTF-8 fails with an
+/// `InvalidData` I/O error.
+pub fn run_to_string(code: &str, input: &[u8]) -> Result<String, SacError> {
+    let output = run(code, input)?;
+
+    String::from_utf8(output).map_err(|_| SacError::Io(io::Error::new(io::ErrorKind::InvalidData, "program output is not valid UTF-8")))
+}
+
+/// Text in, text out, for WASM hosts and playgrounds: `run_to_string` with `input` as UTF-8.
+/// Works without the `std` feature, `run` is the bytes variant.
+pub fn run_program(source: &str, input: &str) -> Result<String, SacError> {
+    run_to_string(source, input.as_bytes())
+}
+
+/// Runs `source` once with each set of optimizations, feeding both runs `input`, and tells whether they
+/// printed the same output and left the same tape. Meant to check new passes against `Optimizations::NONE`.
+/// A run failing, e.g. off the tape, only matches a run failing the same way, only load errors are returned.
+pub fn run_and_compare(source: &str, input: &[u8], optimizations_a: Optimizations, optimizations_b: Optimizations) -> Result<bool, SacError> {
+    let mut runs = Vec::with_capacity(2);
+
+    for optimizations in [optimizations_a, optimizations_b] {
+        let mut interpreter = Interpreter::new();
+        let mut output = Vec::new();
+
+        interpreter.set_optimizations(optimizations);
+        interpreter.load_program_from_str(source)?;
+
+        // Errors compare by kind, the instruction index they carry differs between optimization levels.
+        let outcome = interpreter.interpret_with_io(&mut &input[..], &mut output).map(drop).map_err(|error| match &error {
+            SacError::PointerOutOfBounds(tape_error) => (mem::discriminant(&error), Some(mem::discriminant(tape_error))),
+            _ => (mem::discriminant(&error), None),
+        });
+
+        runs.push((outcome, output, interpreter.ram));
+    }
+
+    Ok(runs[0] == runs[1])
 }
@@ -0,0 +1,112 @@
+#[cfg(feature = "std")]
+use core::time::Duration;
+
+use super::{Cell, EofBehavior, Interpreter, Optimizations, OutputMode, PointerMode, TapeMode};
+
+/// Configures an `Interpreter` in one chained expression, e.g.
+/// `InterpreterBuilder::new().ram_size(30_000).eof_behavior(EofBehavior::Unchanged).max_steps(1_000_000).build()`.
+/// Other cell widths start from `InterpreterBuilder::<u16>::default()`.
+/// Anything left unset keeps the `Interpreter::new` default.
+pub struct InterpreterBuilder<C: Cell = u8> {
+    interpreter: Interpreter<C>,
+}
+
+impl<C: Cell> Default for InterpreterBuilder<C> {
+    fn default() -> Self {
+        InterpreterBuilder { interpreter: Interpreter::default() }
+    }
+}
+
+impl InterpreterBuilder {
+    pub fn new() -> InterpreterBuilder {
+        InterpreterBuilder::default()
+    }
+}
+
+impl<C: Cell> InterpreterBuilder<C> {
+    /// A fixed tape of `size` cells.
+    pub fn ram_size(mut self, size: usize) -> Self {
+        self.interpreter.set_tape_mode(TapeMode::Fixed(size));
+        self
+    }
+
+    /// Alias of `ram_size`.
+    pub fn tape_size(self, size: usize) -> Self {
+        self.ram_size(size)
+    }
+
+    pub fn tape_mode(mut self, tape_mode: TapeMode) -> Self {
+        self.interpreter.set_tape_mode(tape_mode);
+        self
+    }
+
+    pub fn max_ram_size(mut self, max_ram_size: usize) -> Self {
+        self.interpreter.set_max_ram_size(Some(max_ram_size));
+        self
+    }
+
+    pub fn pointer_mode(mut self, pointer_mode: PointerMode) -> Self {
+        self.interpreter.set_pointer_mode(pointer_mode);
+        self
+    }
+
+    pub fn eof_behavior(mut self, eof_behavior: EofBehavior) -> Self {
+        self.interpreter.set_eof_behavior(eof_behavior);
+        self
+    }
+
+    /// Alias of `eof_behavior`.
+    pub fn eof(self, eof_behavior: EofBehavior) -> Self {
+        self.eof_behavior(eof_behavior)
+    }
+
+    pub fn output_mode(mut self, output_mode: OutputMode) -> Self {
+        self.interpreter.set_output_mode(output_mode);
+        self
+    }
+
+    pub fn interactive(mut self, interactive: bool) -> Self {
+        self.interpreter.set_interactive(interactive);
+        self
+    }
+
+    pub fn max_steps(mut self, max: u64) -> Self {
+        self.interpreter.set_max_steps(max);
+        self
+    }
+
+    pub fn max_loop_depth(mut self, depth: usize) -> Self {
+        self.interpreter.set_max_loop_depth(depth);
+        self
+    }
+
+    /// Alias of `max_steps`, see `Interpreter::set_max_cycles`.
+    pub fn max_cycles(self, max: u64) -> Self {
+        self.max_steps(max)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn timeout(mut self, duration: Duration) -> Self {
+        self.interpreter.set_timeout(duration);
+        self
+    }
+
+    pub fn optimizations(mut self, optimizations: Optimizations) -> Self {
+        self.interpreter.set_optimizations(optimizations);
+        self
+    }
+
+    pub fn debug_breakpoints(mut self, enabled: bool) -> Self {
+        self.interpreter.set_debug_breakpoints(enabled);
+        self
+    }
+
+    pub fn profiling(mut self, enabled: bool) -> Self {
+        self.interpreter.set_profiling(enabled);
+        self
+    }
+
+    pub fn build(self) -> Interpreter<C> {
+        self.interpreter
+    }
+}
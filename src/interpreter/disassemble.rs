@@ -0,0 +1,46 @@
+use alloc::string::String;
+use core::fmt::Write;
+
+use super::{Cell, IRInstructionKind, Interpreter};
+
+impl<C: Cell> Interpreter<C> {
+    /// Renders the loaded IR one instruction per line, e.g. `0003  IncrementByte 5` or
+    /// `0008  JumpIfZero -> 0021`. Instructions applied away from the memory pointer end with `@offset`.
+    pub fn disassemble(&self) -> String {
+        let mut listing = String::new();
+
+        for (index, inst) in self.program.iter().enumerate() {
+            write!(listing, "{index:04}  {}", inst.kind).unwrap();
+
+            match inst.kind {
+                IRInstructionKind::JumpIfZero | IRInstructionKind::JumpIfNotZero => write!(listing, " -> {:04}", inst.operand.unwrap()).unwrap(),
+                IRInstructionKind::MultiplyAdd | IRInstructionKind::SetCell => write!(listing, " {}", inst.operand.unwrap() as isize).unwrap(),
+                _ => {
+                    if let Some(operand) = inst.operand {
+                        write!(listing, " {operand}").unwrap();
+                    }
+                },
+            }
+
+            if inst.offset != 0 {
+                write!(listing, " @{:+}", inst.offset).unwrap();
+            }
+
+            listing.push('\n');
+        }
+
+        listing
+    }
+
+    /// Renders the loaded IR with its `Debug` form, one `0003: IncrementByte(5)` line per instruction.
+    /// Jumps show the index of their matching bracket, e.g. `0008: JumpIfZero(-> 21)`.
+    pub fn dump_ir(&self) -> String {
+        let mut dump = String::new();
+
+        for (index, inst) in self.program.iter().enumerate() {
+            writeln!(dump, "{index:04}: {inst:?}").unwrap();
+        }
+
+        dump
+    }
+}
@@ -0,0 +1,269 @@
+//! The I/O traits the interpreter reads input and writes output through.
+//!
+//! With the `std` feature (the default) these are just `std::io`'s. Without it, this module provides
+//! a minimal copy of the same API, implemented for byte slices, `Vec<u8>` and `Cursor`, so embedded
+//! users can implement `Read` and `Write` for their own byte sources and sinks.
+
+#[cfg(feature = "std")]
+pub use std::io::{empty, sink, BufRead, Cursor, Empty, Error, ErrorKind, Read, Result, Sink, Write};
+
+#[cfg(not(feature = "std"))]
+pub use self::core_io::*;
+
+// What the interpreter reads when no input was set: stdin, or an empty reader without `std`.
+#[cfg(feature = "std")]
+pub(crate) fn default_input() -> std::io::StdinLock<'static> {
+    std::io::stdin().lock()
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn default_input() -> Empty {
+    empty()
+}
+
+// Where the output goes when capture is off: stdout, or nowhere without `std`. Buffered, so
+// `Interpreter::set_interactive(false)` saves a write per byte, an interactive run flushes it anyway.
+#[cfg(feature = "std")]
+pub(crate) fn default_output() -> std::io::BufWriter<std::io::StdoutLock<'static>> {
+    std::io::BufWriter::new(std::io::stdout().lock())
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn default_output() -> Sink {
+    sink()
+}
+
+#[cfg(not(feature = "std"))]
+mod core_io {
+    use alloc::vec::Vec;
+    use core::fmt;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ErrorKind {
+        InvalidData,
+        UnexpectedEof,
+        WriteZero,
+        Other,
+    }
+
+    #[derive(Debug)]
+    pub struct Error {
+        kind: ErrorKind,
+        message: &'static str,
+    }
+
+    impl Error {
+        pub fn new(kind: ErrorKind, message: &'static str) -> Error {
+            Error { kind, message }
+        }
+
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.message)
+        }
+    }
+
+    impl core::error::Error for Error {}
+
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    pub trait Read {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+        fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.read(buf)? {
+                    0 => return Err(Error::new(ErrorKind::UnexpectedEof, "failed to fill whole buffer")),
+                    read => buf = &mut buf[read..],
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    pub trait BufRead: Read {
+        fn fill_buf(&mut self) -> Result<&[u8]>;
+
+        fn consume(&mut self, amount: usize);
+    }
+
+    pub trait Write {
+        fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+        fn flush(&mut self) -> Result<()>;
+
+        fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.write(buf)? {
+                    0 => return Err(Error::new(ErrorKind::WriteZero, "failed to write whole buffer")),
+                    written => buf = &buf[written..],
+                }
+            }
+
+            Ok(())
+        }
+
+        /// Backs `write!` and `writeln!`.
+        fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<()> {
+            // Keeps the I/O error, `fmt::Error` can't carry it.
+            struct Adapter<'a, W: Write + ?Sized> {
+                inner: &'a mut W,
+                error: Option<Error>,
+            }
+
+            impl<W: Write + ?Sized> fmt::Write for Adapter<'_, W> {
+                fn write_str(&mut self, s: &str) -> fmt::Result {
+                    self.inner.write_all(s.as_bytes()).map_err(|e| {
+                        self.error = Some(e);
+                        fmt::Error
+                    })
+                }
+            }
+
+            let mut adapter = Adapter { inner: self, error: None };
+
+            match fmt::write(&mut adapter, args) {
+                Ok(()) => Ok(()),
+                Err(_) => Err(adapter.error.unwrap_or(Error::new(ErrorKind::Other, "formatter error"))),
+            }
+        }
+    }
+
+    impl<R: Read + ?Sized> Read for &mut R {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            (**self).read(buf)
+        }
+    }
+
+    impl<R: BufRead + ?Sized> BufRead for &mut R {
+        fn fill_buf(&mut self) -> Result<&[u8]> {
+            (**self).fill_buf()
+        }
+
+        fn consume(&mut self, amount: usize) {
+            (**self).consume(amount)
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            (**self).write(buf)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            (**self).flush()
+        }
+    }
+
+    impl Read for &[u8] {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let read = buf.len().min(self.len());
+
+            buf[..read].copy_from_slice(&self[..read]);
+            *self = &self[read..];
+
+            Ok(read)
+        }
+    }
+
+    impl BufRead for &[u8] {
+        fn fill_buf(&mut self) -> Result<&[u8]> {
+            Ok(*self)
+        }
+
+        fn consume(&mut self, amount: usize) {
+            *self = &self[amount..];
+        }
+    }
+
+    impl Write for Vec<u8> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.extend_from_slice(buf);
+
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Reads from an in-memory buffer, keeping track of the position.
+    #[derive(Debug, Clone, Default)]
+    pub struct Cursor<T> {
+        inner: T,
+        position: u64,
+    }
+
+    impl<T> Cursor<T> {
+        pub fn new(inner: T) -> Cursor<T> {
+            Cursor { inner, position: 0 }
+        }
+
+        pub fn position(&self) -> u64 {
+            self.position
+        }
+
+        pub fn set_position(&mut self, position: u64) {
+            self.position = position;
+        }
+
+        pub fn get_ref(&self) -> &T {
+            &self.inner
+        }
+
+        pub fn into_inner(self) -> T {
+            self.inner
+        }
+    }
+
+    impl<T: AsRef<[u8]>> Read for Cursor<T> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let data = self.inner.as_ref();
+            let start = (self.position as usize).min(data.len());
+            let read = (&data[start..]).read(buf)?;
+
+            self.position += read as u64;
+
+            Ok(read)
+        }
+    }
+
+    /// A reader that is always at EOF, see `empty`.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct Empty;
+
+    pub fn empty() -> Empty {
+        Empty
+    }
+
+    impl Read for Empty {
+        fn read(&mut self, _buf: &mut [u8]) -> Result<usize> {
+            Ok(0)
+        }
+    }
+
+    /// A writer that discards everything, see `sink`.
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct Sink;
+
+    pub fn sink() -> Sink {
+        Sink
+    }
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! The intermediate representation programs are compiled to.
+//!
+//! `compile` returns a `Vec<IRInstruction>` that can be inspected through its accessors, transformed
+//! by custom passes building instructions with `IRInstruction::new` and handed back to
+//! `Interpreter::load_program`. Passes only need to keep the brackets balanced, loading resolves the
+//! jump operands again.
+
+use core::fmt;
+
+/// Position of an instruction in the program source, both starting at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SourceSpan {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl fmt::Display for SourceSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+/// The operation of an `IRInstruction`, see `IRInstruction::new` for what `operand` and `offset` mean.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IRInstructionKind {
+    /// `>` repeated `operand` times.
+    IncrementPointer,
+    /// `<` repeated `operand` times.
+    DecrementPointer,
+    /// `+` repeated `operand` times, on the cell at `offset`.
+    IncrementByte,
+    /// `-` repeated `operand` times, on the cell at `offset`.
+    DecrementByte,
+    PrintByteAsChar,
+    ReadInputToByte,
+    /// `[`, `operand` is the index of the matching `]`.
+    JumpIfZero,
+    /// `]`, `operand` is the index of the matching `[`.
+    JumpIfNotZero,
+    /// Zeroes the cell at `offset`, built from `[-]`, `[+]` and multiply loops.
+    SetZero,
+    /// Adds the current cell times `operand` (a wrapping signed factor) to the cell at `offset`.
+    MultiplyAdd,
+    /// `[>]`, moves right to the nearest zero cell.
+    ScanRight,
+    /// `[<]`, moves left to the nearest zero cell.
+    ScanLeft,
+    /// `#` when debug breakpoints are enabled.
+    Breakpoint,
+    /// Sets the cell at `offset` to `operand` (wrapping at the cell width), built from constant prefixes.
+    SetCell,
+}
+
+impl IRInstructionKind {
+    // Whether instructions of this kind carry a run length, factor or value, rather than no operand
+    // or a jump target resolved when the program is loaded.
+    pub(crate) fn has_operand(self) -> bool {
+        matches!(
+            self,
+            IRInstructionKind::IncrementPointer
+                | IRInstructionKind::DecrementPointer
+                | IRInstructionKind::IncrementByte
+                | IRInstructionKind::DecrementByte
+                | IRInstructionKind::MultiplyAdd
+                | IRInstructionKind::SetCell
+        )
+    }
+}
+
+impl fmt::Display for IRInstructionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The variant names already read as mnemonics.
+        write!(f, "{self:?}")
+    }
+}
+
+/// One instruction of a compiled program, see `compile`. Its `Debug` form shows the operand and offset.
+#[derive(Clone, Copy)]
+pub struct IRInstruction {
+    pub(crate) kind: IRInstructionKind,
+    /// Run length for folded instructions, matching bracket index for jumps,
+    /// factor (wrapping, so negative factors are stored two's complement) for `MultiplyAdd`,
+    /// value for `SetCell`.
+    pub(crate) operand: Option<usize>,
+    /// Cell targeted by byte instructions, relative to the memory pointer.
+    pub(crate) offset: isize,
+    /// Source position of the (first) instruction this was built from.
+    pub(crate) span: SourceSpan,
+}
+
+impl IRInstruction {
+    /// Builds an instruction for a custom pass. `operand` is the run length, matching bracket index,
+    /// `MultiplyAdd` factor or `SetCell` value, and `offset` the cell targeted relative to the memory pointer.
+    pub fn new(kind: IRInstructionKind, operand: Option<usize>, offset: isize, span: SourceSpan) -> IRInstruction {
+        IRInstruction { kind, operand, offset, span }
+    }
+
+    pub fn kind(&self) -> IRInstructionKind {
+        self.kind
+    }
+
+    pub fn operand(&self) -> Option<usize> {
+        self.operand
+    }
+
+    pub fn offset(&self) -> isize {
+        self.offset
+    }
+
+    pub fn span(&self) -> SourceSpan {
+        self.span
+    }
+}
+
+impl fmt::Debug for IRInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.kind, self.operand) {
+            (IRInstructionKind::JumpIfZero | IRInstructionKind::JumpIfNotZero, Some(target)) => write!(f, "{}(-> {target})", self.kind)?,
+            (IRInstructionKind::MultiplyAdd | IRInstructionKind::SetCell, Some(factor)) => write!(f, "{}({})", self.kind, factor as isize)?,
+            (_, Some(operand)) => write!(f, "{}({operand})", self.kind)?,
+            (_, None) => write!(f, "{}", self.kind)?,
+        }
+
+        if self.offset != 0 {
+            write!(f, " @{:+}", self.offset)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use alloc::vec::Vec;
+
+use super::io::{self, Read, Write};
+use super::{Cell, IRInstruction, IRInstructionKind, Interpreter, SacError, SourceSpan};
+
+// An instruction's opcode is its index in this table, so new kinds must only ever be appended.
+const OPCODES: [IRInstructionKind; 14] = [
+    IRInstructionKind::IncrementPointer,
+    IRInstructionKind::DecrementPointer,
+    IRInstructionKind::IncrementByte,
+    IRInstructionKind::DecrementByte,
+    IRInstructionKind::PrintByteAsChar,
+    IRInstructionKind::ReadInputToByte,
+    IRInstructionKind::JumpIfZero,
+    IRInstructionKind::JumpIfNotZero,
+    IRInstructionKind::SetZero,
+    IRInstructionKind::MultiplyAdd,
+    IRInstructionKind::ScanRight,
+    IRInstructionKind::ScanLeft,
+    IRInstructionKind::Breakpoint,
+    IRInstructionKind::SetCell,
+];
+
+// Set in an instruction's first byte, next to its opcode, when an offset or a source position follows.
+const HAS_OFFSET: u8 = 0x40;
+const HAS_SPAN: u8 = 0x80;
+const OPCODE_MASK: u8 = 0x3f;
+
+// Unsigned LEB128: seven bits per byte, low bits first, the high bit set on all but the last byte.
+fn write_varint<W: Write>(w: &mut W, mut value: u64) -> io::Result<()> {
+    let mut bytes = [0u8; 10];
+    let mut len = 0;
+
+    loop {
+        bytes[len] = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            break;
+        }
+
+        bytes[len] |= 0x80;
+        len += 1;
+    }
+
+    w.write_all(&bytes[..=len])
+}
+
+fn read_varint<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut value = 0u64;
+
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8; 1];
+        r.read_exact(&mut byte)?;
+
+        let bits = u64::from(byte[0] & 0x7f);
+
+        if (bits << shift) >> shift != bits {
+            return Err(invalid_data("IR number too large"));
+        }
+
+        value |= bits << shift;
+
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(invalid_data("IR number too large"))
+}
+
+// Zigzag maps signed numbers to unsigned ones so small negative numbers stay short: 0, -1, 1, -2, ...
+fn write_signed_varint<W: Write>(w: &mut W, value: isize) -> io::Result<()> {
+    let value = value as i64;
+
+    write_varint(w, ((value << 1) ^ (value >> 63)) as u64)
+}
+
+fn read_signed_varint<R: Read>(r: &mut R) -> io::Result<isize> {
+    let value = read_varint(r)?;
+    let value = (value >> 1) as i64 ^ -((value & 1) as i64);
+
+    isize::try_from(value).map_err(|_| invalid_data("IR number too large"))
+}
+
+fn read_usize<R: Read>(r: &mut R) -> io::Result<usize> {
+    usize::try_from(read_varint(r)?).map_err(|_| invalid_data("IR number too large"))
+}
+
+fn invalid_data(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl<C: Cell> Interpreter<C> {
+    /// Writes the loaded IR as the instruction count followed by, for each instruction, a byte holding its
+    /// opcode and whether an offset and a source position follow, its operand (run length, factor or value,
+    /// not for jumps), then its offset and its source line and column when they are not zero.
+    /// Numbers are LEB128 varints, the signed factors and offsets zigzag encoded first.
+    pub fn save_ir<W: Write>(&self, w: &mut W) -> Result<(), SacError> {
+        write_varint(w, self.program.len() as u64)?;
+
+        for inst in self.program.iter() {
+            let opcode = OPCODES.iter().position(|&kind| kind == inst.kind).unwrap() as u8;
+            let has_span = inst.span != SourceSpan::default();
+
+            w.write_all(&[opcode | if inst.offset != 0 { HAS_OFFSET } else { 0 } | if has_span { HAS_SPAN } else { 0 }])?;
+
+            // Jump targets are not saved, they are resolved again on load.
+            match (inst.kind, inst.operand) {
+                (IRInstructionKind::MultiplyAdd, Some(factor)) => write_signed_varint(w, factor as isize)?,
+                (kind, Some(operand)) if kind.has_operand() => write_varint(w, operand as u64)?,
+                _ => (),
+            }
+
+            if inst.offset != 0 {
+                write_signed_varint(w, inst.offset)?;
+            }
+
+            if has_span {
+                write_varint(w, inst.span.line as u64)?;
+                write_varint(w, inst.span.col as u64)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the loaded program with IR written by `save_ir` and rewinds the interpreter, like `load_program`.
+    /// Malformed data fails with an `InvalidData` I/O error, unbalanced jumps with `UnbalancedBrackets`.
+    pub fn load_ir<R: Read>(&mut self, r: &mut R) -> Result<(), SacError> {
+        let count = read_varint(r)?;
+        let mut program = Vec::new();
+
+        for _ in 0..count {
+            let mut header = [0u8; 1];
+            r.read_exact(&mut header)?;
+
+            let kind = *OPCODES.get((header[0] & OPCODE_MASK) as usize).ok_or_else(|| invalid_data("unknown IR opcode"))?;
+
+            let operand = match kind {
+                IRInstructionKind::MultiplyAdd => Some(read_signed_varint(r)? as usize),
+                _ if kind.has_operand() => {
+                    let operand = read_usize(r)?;
+
+                    // Larger run lengths and values would turn into negative moves when run.
+                    if operand > isize::MAX as usize {
+                        return Err(invalid_data("IR operand too large").into());
+                    }
+
+                    Some(operand)
+                },
+                _ => None,
+            };
+            let offset = if header[0] & HAS_OFFSET != 0 { read_signed_varint(r)? } else { 0 };
+            let span = if header[0] & HAS_SPAN != 0 { SourceSpan { line: read_usize(r)?, col: read_usize(r)? } } else { SourceSpan::default() };
+
+            program.push(IRInstruction { kind, operand, offset, span });
+        }
+
+        self.load_program(program)
+    }
+}
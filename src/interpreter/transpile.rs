@@ -0,0 +1,170 @@
+use alloc::format;
+use alloc::string::String;
+
+use super::{Cell, EofBehavior, IRInstructionKind, Interpreter, OutputMode};
+
+const INDENT: &str = "    ";
+
+// How byte instructions address their cell in the generated Rust.
+fn rust_cell(offset: isize) -> String {
+    if offset == 0 {
+        String::from("tape[p]")
+    } else if offset > 0 {
+        format!("tape[p + {offset}]")
+    } else {
+        format!("tape[p - {}]", offset.unsigned_abs())
+    }
+}
+
+// How byte instructions address their cell in the generated C.
+fn c_cell(offset: isize) -> String {
+    if offset == 0 {
+        String::from("*p")
+    } else {
+        format!("p[{offset}]")
+    }
+}
+
+impl<C: Cell> Interpreter<C> {
+    /// Emits a standalone C program equivalent to the loaded (optimized) program, using
+    /// the current tape size, cell width, EOF behavior and output mode. Only `stdio.h` is needed to build it.
+    pub fn transpile_to_c(&self) -> String {
+        let mut c_code = String::new();
+        let mut depth = 1usize;
+
+        c_code.push_str("#include <stdio.h>\n\n");
+        c_code.push_str(&format!("static {} tape[{}];\n\n", C::C_TYPE, self.ram.len()));
+        c_code.push_str("int main(void) {\n");
+        c_code.push_str(&format!("{INDENT}{} *p = tape;\n\n", C::C_TYPE));
+
+        for inst in self.program.iter() {
+            let cell = c_cell(inst.offset);
+
+            let line = match inst.kind {
+                IRInstructionKind::IncrementPointer => format!("p += {};", inst.operand.unwrap()),
+                IRInstructionKind::DecrementPointer => format!("p -= {};", inst.operand.unwrap()),
+                IRInstructionKind::IncrementByte => format!("{cell} += {};", inst.operand.unwrap()),
+                IRInstructionKind::DecrementByte => format!("{cell} -= {};", inst.operand.unwrap()),
+                IRInstructionKind::PrintByteAsChar => match self.output_mode {
+                    OutputMode::Char => String::from("putchar((unsigned char)*p);"),
+                    OutputMode::Decimal => String::from("printf(\"%u \", (unsigned int)*p);"),
+                },
+                IRInstructionKind::ReadInputToByte => match self.eof_behavior {
+                    EofBehavior::Unchanged => String::from("{ int c = getchar(); if (c != EOF) *p = c; }"),
+                    EofBehavior::Zero => String::from("{ int c = getchar(); *p = c == EOF ? 0 : c; }"),
+                    EofBehavior::NegativeOne => String::from("{ int c = getchar(); *p = c == EOF ? -1 : c; }"),
+                },
+                IRInstructionKind::JumpIfZero => String::from("while (*p) {"),
+                IRInstructionKind::JumpIfNotZero => {
+                    depth -= 1;
+                    String::from("}")
+                },
+                IRInstructionKind::SetZero => format!("{cell} = 0;"),
+                IRInstructionKind::SetCell => format!("{cell} = {};", inst.operand.unwrap() & C::MAX.to_operand()),
+                // Guarded like the interpreter, the target may be out of the tape when the loop would not have run.
+                IRInstructionKind::MultiplyAdd => format!("if (*p) {cell} += *p * {};", inst.operand.unwrap() as isize),
+                IRInstructionKind::ScanRight => String::from("while (*p) p++;"),
+                IRInstructionKind::ScanLeft => String::from("while (*p) p--;"),
+                IRInstructionKind::Breakpoint => String::from("/* breakpoint */"),
+            };
+
+            c_code.push_str(&INDENT.repeat(depth));
+            c_code.push_str(&line);
+            c_code.push('\n');
+
+            if inst.kind == IRInstructionKind::JumpIfZero {
+                depth += 1;
+            }
+        }
+
+        c_code.push_str(&format!("\n{INDENT}return 0;\n"));
+        c_code.push_str("}\n");
+
+        c_code
+    }
+
+    /// Emits a standalone Rust program (a single `fn main`) equivalent to the loaded (optimized) program,
+    /// using the current tape size, cell width, EOF behavior and output mode, with wrapping cell arithmetic.
+    pub fn transpile_to_rust(&self) -> String {
+        let mut rust_code = String::new();
+        let mut depth = 1usize;
+
+        // Operands are stored wrapping, masking them gives the same value as a literal of the cell type.
+        let mask = C::MAX.to_operand();
+        let reads = self.program.iter().any(|inst| inst.kind == IRInstructionKind::ReadInputToByte);
+        let writes = self.program.iter().any(|inst| inst.kind == IRInstructionKind::PrintByteAsChar);
+
+        match (reads, writes) {
+            (true, true) => rust_code.push_str("use std::io::{Read, Write};\n\n"),
+            (true, false) => rust_code.push_str("use std::io::Read;\n\n"),
+            (false, true) => rust_code.push_str("use std::io::Write;\n\n"),
+            (false, false) => (),
+        }
+
+        rust_code.push_str("fn main() {\n");
+        rust_code.push_str(&format!("{INDENT}let mut tape = vec![0{}; {}];\n", C::RUST_TYPE, self.ram.len()));
+        rust_code.push_str(&format!("{INDENT}let mut p = 0usize;\n"));
+
+        if reads {
+            rust_code.push_str(&format!("{INDENT}let mut input = std::io::stdin().lock();\n"));
+        }
+
+        if writes {
+            rust_code.push_str(&format!("{INDENT}let mut output = std::io::stdout().lock();\n"));
+        }
+
+        rust_code.push('\n');
+
+        for inst in self.program.iter() {
+            let cell = rust_cell(inst.offset);
+
+            let line = match inst.kind {
+                IRInstructionKind::IncrementPointer => format!("p += {};", inst.operand.unwrap()),
+                IRInstructionKind::DecrementPointer => format!("p -= {};", inst.operand.unwrap()),
+                IRInstructionKind::IncrementByte => format!("{cell} = {cell}.wrapping_add({});", inst.operand.unwrap() & mask),
+                IRInstructionKind::DecrementByte => format!("{cell} = {cell}.wrapping_sub({});", inst.operand.unwrap() & mask),
+                IRInstructionKind::PrintByteAsChar => match self.output_mode {
+                    OutputMode::Char => String::from("output.write_all(&[tape[p] as u8]).unwrap();"),
+                    OutputMode::Decimal => String::from("write!(output, \"{} \", tape[p]).unwrap();"),
+                },
+                IRInstructionKind::ReadInputToByte => {
+                    let on_eof = match self.eof_behavior {
+                        EofBehavior::Unchanged => String::new(),
+                        EofBehavior::Zero => String::from(" else { tape[p] = 0; }"),
+                        EofBehavior::NegativeOne => format!(" else {{ tape[p] = {}::MAX; }}", C::RUST_TYPE),
+                    };
+
+                    format!("{{ let mut byte = [0u8; 1]; if input.read(&mut byte).unwrap() == 1 {{ tape[p] = byte[0] as {}; }}{on_eof} }}", C::RUST_TYPE)
+                },
+                IRInstructionKind::JumpIfZero => String::from("while tape[p] != 0 {"),
+                IRInstructionKind::JumpIfNotZero => {
+                    depth -= 1;
+                    String::from("}")
+                },
+                IRInstructionKind::SetZero => format!("{cell} = 0;"),
+                IRInstructionKind::SetCell => format!("{cell} = {};", inst.operand.unwrap() & mask),
+                // Guarded like the interpreter, the target may be out of the tape when the loop would not have run.
+                IRInstructionKind::MultiplyAdd => format!("if tape[p] != 0 {{ {cell} = {cell}.wrapping_add(tape[p].wrapping_mul({})); }}", inst.operand.unwrap() & mask),
+                IRInstructionKind::ScanRight => String::from("while tape[p] != 0 { p += 1; }"),
+                IRInstructionKind::ScanLeft => String::from("while tape[p] != 0 { p -= 1; }"),
+                IRInstructionKind::Breakpoint => String::from("// breakpoint"),
+            };
+
+            rust_code.push_str(&INDENT.repeat(depth));
+            rust_code.push_str(&line);
+            rust_code.push('\n');
+
+            if inst.kind == IRInstructionKind::JumpIfZero {
+                depth += 1;
+            }
+        }
+
+        if writes {
+            rust_code.push_str(&format!("\n{INDENT}output.flush().unwrap();\n"));
+        }
+
+        rust_code.push_str("}\n");
+
+        rust_code
+    }
+}
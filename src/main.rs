@@ -1,20 +1,145 @@
-use std::{env, process};
-use sac::interpreter::Interpreter;
+use std::str::FromStr;
+use std::{env, io, process};
+use sac::interpreter::{EofBehavior, Interpreter, OutputMode};
+
+struct Options {
+    program_path: String,
+    tape_size: Option<usize>,
+    eof_behavior: EofBehavior,
+    output_mode: OutputMode,
+    no_flush: bool,
+    max_cycles: Option<u64>,
+    dump_tape: bool,
+}
+
+fn usage() -> ! {
+    eprintln!("[ERROR] Usage : ./sac [options] <my_program.bf | ->");
+    eprintln!("[ERROR] Options : --tape-size=<cells> --eof=zero|unchanged|neg1 --numeric-output --no-flush --max-cycles=<count> --dump-tape");
+    process::exit(1);
+}
+
+// The value of `flag`, given either as `--flag=value` or as the next argument.
+fn flag_value<'a, T: FromStr>(flag: &str, inline_value: Option<&'a str>, rest: &mut impl Iterator<Item = &'a String>) -> T {
+    let value = inline_value.or_else(|| rest.next().map(String::as_str)).unwrap_or_else(|| {
+        eprintln!("[ERROR] Missing value for '{flag}'");
+        usage()
+    });
+
+    value.parse().unwrap_or_else(|_| {
+        eprintln!("[ERROR] Invalid value '{value}' for '{flag}'");
+        usage()
+    })
+}
+
+fn parse_args(args: &[String]) -> Options {
+    let mut options = Options {
+        // Without a path, or with `-`, the source is read from stdin until EOF. The source is consumed
+        // first, so `,` in the program then reads from the same, exhausted, stdin and only sees EOF.
+        program_path: String::from("-"),
+        tape_size: None,
+        eof_behavior: EofBehavior::default(),
+        output_mode: OutputMode::default(),
+        no_flush: false,
+        max_cycles: None,
+        dump_tape: false,
+    };
+    let mut program_path = None;
+    let mut rest = args.iter().skip(1);
+
+    while let Some(arg) = rest.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if arg.starts_with("--") => (flag, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+
+        match flag {
+            "--tape-size" => options.tape_size = Some(flag_value(flag, inline_value, &mut rest)),
+            "--max-cycles" => options.max_cycles = Some(flag_value(flag, inline_value, &mut rest)),
+            "--eof" => {
+                let value: String = flag_value(flag, inline_value, &mut rest);
+
+                options.eof_behavior = match value.as_str() {
+                    "zero" => EofBehavior::Zero,
+                    "unchanged" => EofBehavior::Unchanged,
+                    "neg1" => EofBehavior::NegativeOne,
+                    _ => {
+                        eprintln!("[ERROR] Invalid value '{value}' for '--eof'");
+                        usage()
+                    },
+                };
+            },
+            "--numeric-output" | "--no-flush" | "--dump-tape" if inline_value.is_some() => {
+                eprintln!("[ERROR] Option '{flag}' takes no value");
+                usage()
+            },
+            "--numeric-output" => options.output_mode = OutputMode::Decimal,
+            "--no-flush" => options.no_flush = true,
+            "--dump-tape" => options.dump_tape = true,
+            _ if flag.starts_with("--") => {
+                eprintln!("[ERROR] Unknown option '{arg}'");
+                usage()
+            },
+            _ if program_path.is_none() => program_path = Some(arg.clone()),
+            _ => {
+                eprintln!("[ERROR] More than one program provided !");
+                usage()
+            },
+        }
+    }
+
+    if let Some(program_path) = program_path {
+        options.program_path = program_path;
+    }
+
+    options
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
+    let options = parse_args(&args);
+    let program_path = options.program_path.as_str();
+
+    let mut my_interpreter = match options.tape_size {
+        Some(size) => Interpreter::with_tape_size(size),
+        None => Interpreter::new(),
+    };
+
+    my_interpreter.set_eof_behavior(options.eof_behavior);
+    my_interpreter.set_output_mode(options.output_mode);
+    my_interpreter.set_interactive(!options.no_flush);
+
+    if let Some(max_cycles) = options.max_cycles {
+        my_interpreter.set_max_cycles(max_cycles);
+    }
+
+    let loaded = if program_path == "-" {
+        my_interpreter.load_program_from_reader(io::stdin().lock())
+    } else {
+        my_interpreter.load_program_from_file(program_path)
+    };
 
-    if args.len() <= 1 {
-        eprintln!("[ERROR] No program provided !");
-        eprintln!("[ERROR] Usage : ./sac <my_program.bf>");
+    if let Err(e) = loaded {
+        eprintln!("[ERROR] '{program_path}' : {e}");
         process::exit(1);
     }
 
-    let program_path = &args[1];
+    if my_interpreter.validate().is_ok_and(|stats| stats.is_empty()) {
+        eprintln!("[WARNING] '{program_path}' : Program has no effect, it compiles to no instructions");
+    }
 
-    let mut my_interpreter = Interpreter::new();
+    if let Err(e) = my_interpreter.interpret() {
+        eprintln!("[ERROR] {e}");
+        process::exit(1);
+    }
 
-    my_interpreter.load_program_from_file(program_path);
+    if options.dump_tape {
+        // From the first to the last non-zero cell, on stderr to keep it apart from the program's output.
+        let tape = my_interpreter.tape();
 
-    my_interpreter.interpret();
+        if let (Some(first), Some(last)) = (tape.iter().position(|&cell| cell != 0), tape.iter().rposition(|&cell| cell != 0)) {
+            for (index, cell) in tape.iter().enumerate().take(last + 1).skip(first) {
+                eprintln!("{index}: {cell}");
+            }
+        }
+    }
 }